@@ -1,25 +1,19 @@
-use std::{
-    cell::{RefCell, UnsafeCell},
-    num::NonZeroU32,
-    time::Instant,
-};
-
 use bincode::Options;
 use eliecs::components;
 use serde::{Deserialize, Serialize};
 
 components! {
-    #[derive(Debug, Serialize, Deserialize, Clone)]
-    struct CPosition {
+    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+    pub struct CPosition {
         pub x: f32,
         pub y: f32,
         pub z: f32,
     }
-    #[derive(Debug, Serialize, Deserialize, Clone)]
-    struct CName(pub String);
+    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+    pub struct CName(pub String);
 
-    #[derive(Debug, Serialize, Deserialize, Clone)]
-    struct CRot(pub f32);
+    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+    pub struct CRot(pub f32);
 }
 
 fn main() {
@@ -51,7 +45,7 @@ fn main() {
     dbg!(ecs.rot(e.id));
 
     // dbg!(ecs.destroyed);
-    for (id, v) in ecs.query_position_mut() {
+    for (_id, v) in ecs.query_position_mut() {
         v.x += 1.0;
     }
 
@@ -0,0 +1,150 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt,
+    ops::{Deref, DerefMut},
+};
+
+use crate::{EcsError, Entity};
+
+type Key = (&'static str, u32);
+
+/// The components handed out by the generated `try_*` accessors, so they can refuse a `&mut`
+/// next to another reference instead of aliasing it. Kept by the generated `Ecs`.
+#[derive(Debug, Default)]
+pub struct BorrowFlags {
+    /// Shared borrows count up, an exclusive one is `-1`.
+    flags: RefCell<HashMap<Key, isize>>,
+}
+
+impl BorrowFlags {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Borrows `component` of `entity` as fetched by `get`, failing if it is borrowed mutably.
+    pub fn borrow<'a, T: ?Sized>(
+        &'a self,
+        entity: Entity,
+        component: &'static str,
+        get: impl FnOnce() -> Result<&'a T, EcsError>,
+    ) -> Result<ComponentRef<'a, T>, EcsError> {
+        let key = (component, entity.id);
+        if self.flags.borrow().get(&key).is_some_and(|&n| n < 0) {
+            return Err(EcsError::BorrowConflict { entity, component });
+        }
+        let value = get()?;
+        *self.flags.borrow_mut().entry(key).or_insert(0) += 1;
+        Ok(ComponentRef {
+            value,
+            flags: self,
+            key,
+        })
+    }
+
+    /// Borrows `component` of `entity` mutably as fetched by `get`, failing if it is borrowed
+    /// at all.
+    pub fn borrow_mut<'a, T: ?Sized>(
+        &'a self,
+        entity: Entity,
+        component: &'static str,
+        get: impl FnOnce() -> Result<&'a mut T, EcsError>,
+    ) -> Result<ComponentRefMut<'a, T>, EcsError> {
+        let key = (component, entity.id);
+        if self.flags.borrow().contains_key(&key) {
+            return Err(EcsError::BorrowConflict { entity, component });
+        }
+        let value = get()?;
+        self.flags.borrow_mut().insert(key, -1);
+        Ok(ComponentRefMut {
+            value,
+            flags: self,
+            key,
+        })
+    }
+
+    /// Fails if any `component` is borrowed: adding or removing one to `entity` can move the
+    /// others.
+    pub fn check_unborrowed(
+        &self,
+        entity: Entity,
+        component: &'static str,
+    ) -> Result<(), EcsError> {
+        if self.flags.borrow().keys().any(|(c, _)| *c == component) {
+            return Err(EcsError::BorrowConflict { entity, component });
+        }
+        Ok(())
+    }
+
+    fn release(&self, key: Key) {
+        let mut flags = self.flags.borrow_mut();
+        if let Some(n) = flags.get_mut(&key) {
+            if *n > 1 {
+                *n -= 1;
+            } else {
+                flags.remove(&key);
+            }
+        }
+    }
+}
+
+/// A component borrowed through a generated `try_*` accessor, released when dropped.
+pub struct ComponentRef<'a, T: ?Sized> {
+    value: &'a T,
+    flags: &'a BorrowFlags,
+    key: Key,
+}
+
+impl<T: ?Sized> Deref for ComponentRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<T: ?Sized> Drop for ComponentRef<'_, T> {
+    fn drop(&mut self) {
+        self.flags.release(self.key);
+    }
+}
+
+impl<T: fmt::Debug + ?Sized> fmt::Debug for ComponentRef<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+/// A component borrowed mutably through a generated `try_*_mut` accessor, released when
+/// dropped.
+pub struct ComponentRefMut<'a, T: ?Sized> {
+    value: &'a mut T,
+    flags: &'a BorrowFlags,
+    key: Key,
+}
+
+impl<T: ?Sized> Deref for ComponentRefMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<T: ?Sized> DerefMut for ComponentRefMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.value
+    }
+}
+
+impl<T: ?Sized> Drop for ComponentRefMut<'_, T> {
+    fn drop(&mut self) {
+        self.flags.release(self.key);
+    }
+}
+
+impl<T: fmt::Debug + ?Sized> fmt::Debug for ComponentRefMut<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
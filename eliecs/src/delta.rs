@@ -0,0 +1,47 @@
+use crate::Entity;
+
+/// What changed between two states of a world, as computed by the generated `Ecs::diff` and
+/// applied with `Ecs::apply_delta`. `C` is the generated `ComponentTypeContaining` and `T` the
+/// generated `ComponentType`.
+///
+/// Components of spawned entities are listed in `inserted`; despawned entities don't list the
+/// components they lost.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct WorldDelta<C, T> {
+    pub spawned: Vec<Entity>,
+    pub despawned: Vec<Entity>,
+    pub inserted: Vec<(Entity, C)>,
+    pub updated: Vec<(Entity, C)>,
+    pub removed: Vec<(Entity, T)>,
+    /// The new free list, if it changed or entities were spawned or despawned. Applying it makes
+    /// future spawns hand out the same entities as they would in the diffed world.
+    pub free_list: Option<Vec<Entity>>,
+}
+
+impl<C, T> WorldDelta<C, T> {
+    pub fn new() -> Self {
+        Self {
+            spawned: Vec::new(),
+            despawned: Vec::new(),
+            inserted: Vec::new(),
+            updated: Vec::new(),
+            removed: Vec::new(),
+            free_list: None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spawned.is_empty()
+            && self.despawned.is_empty()
+            && self.inserted.is_empty()
+            && self.updated.is_empty()
+            && self.removed.is_empty()
+            && self.free_list.is_none()
+    }
+}
+
+impl<C, T> Default for WorldDelta<C, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
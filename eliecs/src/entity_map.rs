@@ -0,0 +1,138 @@
+use std::{cell::Cell, collections::HashMap};
+
+use crate::Entity;
+
+/// Maps entities of one world onto the entities they became in another, e.g. after loading a
+/// scene into a non-empty `Ecs`.
+#[derive(Clone, Debug, Default)]
+pub struct EntityMap {
+    map: HashMap<Entity, Entity>,
+    /// What [`MapEntities`] turns entities missing from the map into, if anything.
+    fallback: Option<Entity>,
+    /// How many entities [`MapEntities`] has looked up that weren't in the map.
+    misses: Cell<usize>,
+}
+
+impl PartialEq for EntityMap {
+    fn eq(&self, other: &Self) -> bool {
+        self.map == other.map && self.fallback == other.fallback
+    }
+}
+
+impl Eq for EntityMap {}
+
+impl EntityMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, from: Entity, to: Entity) -> Option<Entity> {
+        self.map.insert(from, to)
+    }
+
+    pub fn get(&self, from: Entity) -> Option<Entity> {
+        self.map.get(&from).copied()
+    }
+
+    /// Where [`MapEntities`] sends `from`: its entry, or the fallback if it has none.
+    pub fn map(&self, from: Entity) -> Option<Entity> {
+        let to = self.get(from);
+        if to.is_none() {
+            self.misses.set(self.misses.get() + 1);
+        }
+        to.or(self.fallback)
+    }
+
+    /// Makes [`MapEntities`] turn entities missing from the map into `fallback` rather than
+    /// leaving them alone, e.g. into [`Entity::DANGLING`] so they can't end up naming an unrelated
+    /// entity of the other world.
+    pub fn set_fallback(&mut self, fallback: Option<Entity>) {
+        self.fallback = fallback;
+    }
+
+    /// How many entities missing from the map [`MapEntities`] has looked up so far.
+    pub fn misses(&self) -> usize {
+        self.misses.get()
+    }
+
+    pub fn remove(&mut self, from: Entity) -> Option<Entity> {
+        self.map.remove(&from)
+    }
+
+    pub fn contains(&self, from: Entity) -> bool {
+        self.map.contains_key(&from)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, Entity)> + '_ {
+        self.map.iter().map(|(k, v)| (*k, *v))
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+/// Rewrites the entities stored inside a value through an [`EntityMap`]. Entities missing from the
+/// map are left untouched, unless the map has a fallback.
+///
+/// Components usually get this through `components!`, either by marking their entity fields with
+/// `#[entity]`:
+///
+/// ```ignore
+/// components! {
+///     struct CTarget {
+///         #[entity]
+///         pub target: Entity,
+///         pub range: f32,
+///     }
+/// }
+/// ```
+///
+/// or, for anything more involved, by putting `#[map_entities]` on the struct and implementing this
+/// trait by hand. Loading a scene or appending one `Ecs` to another calls it on every component
+/// that was moved.
+pub trait MapEntities {
+    fn map_entities(&mut self, map: &EntityMap);
+}
+
+impl MapEntities for Entity {
+    fn map_entities(&mut self, map: &EntityMap) {
+        if let Some(e) = map.map(*self) {
+            *self = e;
+        }
+    }
+}
+
+impl<T: MapEntities> MapEntities for Option<T> {
+    fn map_entities(&mut self, map: &EntityMap) {
+        if let Some(v) = self {
+            v.map_entities(map);
+        }
+    }
+}
+
+impl<T: MapEntities> MapEntities for Vec<T> {
+    fn map_entities(&mut self, map: &EntityMap) {
+        for v in self {
+            v.map_entities(map);
+        }
+    }
+}
+
+impl<T: MapEntities, const N: usize> MapEntities for [T; N] {
+    fn map_entities(&mut self, map: &EntityMap) {
+        for v in self {
+            v.map_entities(map);
+        }
+    }
+}
+
+impl<T: MapEntities> MapEntities for Box<T> {
+    fn map_entities(&mut self, map: &EntityMap) {
+        (**self).map_entities(map);
+    }
+}
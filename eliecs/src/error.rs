@@ -0,0 +1,42 @@
+use crate::Entity;
+
+/// Why a generated `try_*` method failed. Their non-`try` versions panic or do nothing instead.
+///
+/// Components are named as declared, e.g. `"CPosition"`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EcsError {
+    /// The entity was despawned, or its id was reused by a newer entity.
+    DeadEntity(Entity),
+    MissingComponent {
+        entity: Entity,
+        component: &'static str,
+    },
+    /// Every entity id is alive, so nothing more can be spawned.
+    CapacityExceeded,
+    /// The component is still borrowed through a `try_*` accessor in a way that rules this out.
+    BorrowConflict {
+        entity: Entity,
+        component: &'static str,
+    },
+}
+
+impl std::fmt::Display for EcsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EcsError::DeadEntity(e) => write!(f, "entity {e:?} isn't alive"),
+            EcsError::MissingComponent { entity, component } => {
+                write!(f, "entity {entity:?} doesn't have component {component}")
+            }
+            EcsError::CapacityExceeded => f.write_str("no entity ids left"),
+            EcsError::BorrowConflict { entity, component } => {
+                write!(
+                    f,
+                    "component {component} of entity {entity:?} is already borrowed"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for EcsError {}
@@ -0,0 +1,461 @@
+//! Hashing of world state that is the same on every machine, for comparing worlds across peers.
+//!
+//! Components are hashed through their `Serialize` impl, so they don't need to implement `Hash`
+//! and floats work: they are hashed by their bits, with `-0.0` hashed as `0.0` and every NaN as
+//! the same NaN. Map entries are hashed one by one and summed up, so a `HashMap` hashes the same
+//! whatever order it iterates in.
+
+use std::hash::Hasher;
+
+use serde::{ser, Serialize};
+
+use crate::Pool;
+
+/// 64 bit FNV-1a. Unlike `DefaultHasher` its output is fixed, not just for one build.
+#[derive(Clone, Copy, Debug)]
+pub struct StableHasher(u64);
+
+impl StableHasher {
+    pub fn new() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= *b as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    // the defaults use native endianness
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+}
+
+/// Hashes `v` with a [`StableHasher`].
+pub fn stable_hash<T: Serialize + ?Sized>(v: &T) -> Result<u64, HashError> {
+    let mut hasher = StableHasher::new();
+    hash_into(v, &mut hasher)?;
+    Ok(hasher.finish())
+}
+
+/// Feeds `v` into `hasher` through its `Serialize` impl. Fails when the `Serialize` impl does.
+pub fn hash_into<T: Serialize + ?Sized, H: Hasher>(v: &T, hasher: &mut H) -> Result<(), HashError> {
+    v.serialize(HashSerializer(hasher))
+}
+
+/// Hashes the values of `pool` in index order, so two pools with the same contents hash the same
+/// no matter how their dense arrays are ordered.
+pub fn hash_pool<T: Serialize>(pool: &Pool<T>) -> Result<u64, HashError> {
+    let mut entries = pool.iter().collect::<Vec<_>>();
+    entries.sort_unstable_by_key(|(i, _)| *i);
+    let mut hasher = StableHasher::new();
+    hasher.write_u64(entries.len() as u64);
+    for (i, v) in entries {
+        hasher.write_u32(i);
+        hash_into(v, &mut hasher)?;
+    }
+    Ok(hasher.finish())
+}
+
+/// The parts of a world's state hash, as returned by the generated `Ecs::state_hashes`. `T` is
+/// the generated `ComponentType`.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct StateHashes<T> {
+    pub existence: u64,
+    pub free_list: u64,
+    pub components: Vec<(T, u64)>,
+}
+
+impl<T: Copy + PartialEq> StateHashes<T> {
+    /// One hash of the whole world, what the generated `Ecs::state_hash` returns.
+    pub fn combined(&self) -> u64 {
+        let mut hasher = StableHasher::new();
+        hasher.write_u64(self.existence);
+        hasher.write_u64(self.free_list);
+        for (_, h) in &self.components {
+            hasher.write_u64(*h);
+        }
+        hasher.finish()
+    }
+
+    /// The components whose pools hash differently in `other`.
+    pub fn desynced_components(&self, other: &Self) -> Vec<T> {
+        self.components
+            .iter()
+            .filter(|(ty, h)| {
+                !other
+                    .components
+                    .iter()
+                    .any(|(other_ty, other_h)| ty == other_ty && h == other_h)
+            })
+            .map(|(ty, _)| *ty)
+            .collect()
+    }
+}
+
+/// A `Serialize` impl failed while hashing, e.g. a `Mutex` that was poisoned.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HashError(pub String);
+
+impl std::fmt::Display for HashError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "couldn't hash: {}", self.0)
+    }
+}
+
+impl std::error::Error for HashError {}
+
+impl ser::Error for HashError {
+    fn custom<M: std::fmt::Display>(msg: M) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+struct HashSerializer<'a, H>(&'a mut H);
+
+/// Hashes every entry with its own hasher and adds the results, since addition doesn't care
+/// about order.
+struct MapHasher<'a, H> {
+    out: &'a mut H,
+    len: u64,
+    sum: u64,
+    entry: StableHasher,
+}
+
+// every value is prefixed with a tag so that e.g. `(Some(1), None)` and `(None, Some(1))` can't
+// collide
+const TAG_UNIT: u8 = 0;
+const TAG_BOOL: u8 = 1;
+const TAG_INT: u8 = 2;
+const TAG_FLOAT: u8 = 3;
+const TAG_CHAR: u8 = 4;
+const TAG_STR: u8 = 5;
+const TAG_BYTES: u8 = 6;
+const TAG_NONE: u8 = 7;
+const TAG_SOME: u8 = 8;
+const TAG_VARIANT: u8 = 9;
+const TAG_SEQ: u8 = 10;
+const TAG_MAP: u8 = 11;
+const TAG_END: u8 = 12;
+
+impl<H: Hasher> HashSerializer<'_, H> {
+    fn float(self, v: f64) {
+        let v = if v == 0.0 {
+            0.0
+        } else if v.is_nan() {
+            f64::NAN
+        } else {
+            v
+        };
+        self.0.write_u8(TAG_FLOAT);
+        self.0.write_u64(v.to_bits());
+    }
+
+    fn int(self, v: i128) {
+        self.0.write_u8(TAG_INT);
+        self.0.write_i128(v);
+    }
+
+    fn variant(&mut self, index: u32) {
+        self.0.write_u8(TAG_VARIANT);
+        self.0.write_u32(index);
+    }
+
+    fn reborrow(&mut self) -> HashSerializer<'_, H> {
+        HashSerializer(self.0)
+    }
+}
+
+impl<'a, H: Hasher> ser::Serializer for HashSerializer<'a, H> {
+    type Ok = ();
+    type Error = HashError;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = MapHasher<'a, H>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, v: bool) -> Result<(), HashError> {
+        self.0.write_u8(TAG_BOOL);
+        self.0.write_u8(v as u8);
+        Ok(())
+    }
+    fn serialize_i8(self, v: i8) -> Result<(), HashError> {
+        self.int(v as i128);
+        Ok(())
+    }
+    fn serialize_i16(self, v: i16) -> Result<(), HashError> {
+        self.int(v as i128);
+        Ok(())
+    }
+    fn serialize_i32(self, v: i32) -> Result<(), HashError> {
+        self.int(v as i128);
+        Ok(())
+    }
+    fn serialize_i64(self, v: i64) -> Result<(), HashError> {
+        self.int(v as i128);
+        Ok(())
+    }
+    fn serialize_i128(self, v: i128) -> Result<(), HashError> {
+        self.int(v);
+        Ok(())
+    }
+    fn serialize_u8(self, v: u8) -> Result<(), HashError> {
+        self.int(v as i128);
+        Ok(())
+    }
+    fn serialize_u16(self, v: u16) -> Result<(), HashError> {
+        self.int(v as i128);
+        Ok(())
+    }
+    fn serialize_u32(self, v: u32) -> Result<(), HashError> {
+        self.int(v as i128);
+        Ok(())
+    }
+    fn serialize_u64(self, v: u64) -> Result<(), HashError> {
+        self.int(v as i128);
+        Ok(())
+    }
+    fn serialize_u128(self, v: u128) -> Result<(), HashError> {
+        self.0.write_u8(TAG_INT);
+        self.0.write_u128(v);
+        Ok(())
+    }
+    fn serialize_f32(self, v: f32) -> Result<(), HashError> {
+        self.float(v as f64);
+        Ok(())
+    }
+    fn serialize_f64(self, v: f64) -> Result<(), HashError> {
+        self.float(v);
+        Ok(())
+    }
+    fn serialize_char(self, v: char) -> Result<(), HashError> {
+        self.0.write_u8(TAG_CHAR);
+        self.0.write_u32(v as u32);
+        Ok(())
+    }
+    fn serialize_str(self, v: &str) -> Result<(), HashError> {
+        self.0.write_u8(TAG_STR);
+        self.0.write_u64(v.len() as u64);
+        self.0.write(v.as_bytes());
+        Ok(())
+    }
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), HashError> {
+        self.0.write_u8(TAG_BYTES);
+        self.0.write_u64(v.len() as u64);
+        self.0.write(v);
+        Ok(())
+    }
+    fn serialize_none(self) -> Result<(), HashError> {
+        self.0.write_u8(TAG_NONE);
+        Ok(())
+    }
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), HashError> {
+        self.0.write_u8(TAG_SOME);
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<(), HashError> {
+        self.0.write_u8(TAG_UNIT);
+        Ok(())
+    }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), HashError> {
+        self.serialize_unit()
+    }
+    fn serialize_unit_variant(
+        mut self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<(), HashError> {
+        self.variant(variant_index);
+        Ok(())
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), HashError> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        mut self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<(), HashError> {
+        self.variant(variant_index);
+        value.serialize(self)
+    }
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self, HashError> {
+        self.0.write_u8(TAG_SEQ);
+        Ok(self)
+    }
+    fn serialize_tuple(self, len: usize) -> Result<Self, HashError> {
+        self.serialize_seq(Some(len))
+    }
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<Self, HashError> {
+        self.serialize_seq(Some(len))
+    }
+    fn serialize_tuple_variant(
+        mut self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        len: usize,
+    ) -> Result<Self, HashError> {
+        self.variant(variant_index);
+        self.serialize_seq(Some(len))
+    }
+    fn serialize_map(self, _len: Option<usize>) -> Result<MapHasher<'a, H>, HashError> {
+        self.0.write_u8(TAG_MAP);
+        Ok(MapHasher {
+            out: self.0,
+            len: 0,
+            sum: 0,
+            entry: StableHasher::new(),
+        })
+    }
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self, HashError> {
+        self.serialize_seq(Some(len))
+    }
+    fn serialize_struct_variant(
+        mut self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        len: usize,
+    ) -> Result<Self, HashError> {
+        self.variant(variant_index);
+        self.serialize_seq(Some(len))
+    }
+}
+
+impl<H: Hasher> ser::SerializeSeq for HashSerializer<'_, H> {
+    type Ok = ();
+    type Error = HashError;
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), HashError> {
+        value.serialize(self.reborrow())
+    }
+    fn end(self) -> Result<(), HashError> {
+        self.0.write_u8(TAG_END);
+        Ok(())
+    }
+}
+
+impl<H: Hasher> ser::SerializeTuple for HashSerializer<'_, H> {
+    type Ok = ();
+    type Error = HashError;
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), HashError> {
+        value.serialize(self.reborrow())
+    }
+    fn end(self) -> Result<(), HashError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl<H: Hasher> ser::SerializeTupleStruct for HashSerializer<'_, H> {
+    type Ok = ();
+    type Error = HashError;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), HashError> {
+        value.serialize(self.reborrow())
+    }
+    fn end(self) -> Result<(), HashError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl<H: Hasher> ser::SerializeTupleVariant for HashSerializer<'_, H> {
+    type Ok = ();
+    type Error = HashError;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), HashError> {
+        value.serialize(self.reborrow())
+    }
+    fn end(self) -> Result<(), HashError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl<H: Hasher> ser::SerializeMap for MapHasher<'_, H> {
+    type Ok = ();
+    type Error = HashError;
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), HashError> {
+        self.entry = StableHasher::new();
+        key.serialize(HashSerializer(&mut self.entry))
+    }
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), HashError> {
+        value.serialize(HashSerializer(&mut self.entry))?;
+        self.len += 1;
+        self.sum = self.sum.wrapping_add(self.entry.finish());
+        Ok(())
+    }
+    fn end(self) -> Result<(), HashError> {
+        self.out.write_u64(self.len);
+        self.out.write_u64(self.sum);
+        self.out.write_u8(TAG_END);
+        Ok(())
+    }
+}
+
+// field names aren't hashed: they're the same for every value of a type
+impl<H: Hasher> ser::SerializeStruct for HashSerializer<'_, H> {
+    type Ok = ();
+    type Error = HashError;
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<(), HashError> {
+        value.serialize(self.reborrow())
+    }
+    fn end(self) -> Result<(), HashError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl<H: Hasher> ser::SerializeStructVariant for HashSerializer<'_, H> {
+    type Ok = ();
+    type Error = HashError;
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<(), HashError> {
+        value.serialize(self.reborrow())
+    }
+    fn end(self) -> Result<(), HashError> {
+        ser::SerializeSeq::end(self)
+    }
+}
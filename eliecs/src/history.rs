@@ -0,0 +1,191 @@
+use crate::Mutation;
+
+/// The mutations made by one transaction, along with the mutations that undo them.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Transaction<C, T> {
+    pub forward: Vec<Mutation<C, T>>,
+    /// Applied in reverse order to undo the transaction.
+    pub inverse: Vec<Mutation<C, T>>,
+}
+
+impl<C, T> Transaction<C, T> {
+    pub fn new() -> Self {
+        Self {
+            forward: Vec::new(),
+            inverse: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.forward.is_empty()
+    }
+}
+
+impl<C, T> Default for Transaction<C, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Undo and redo stacks of transactions, kept by the generated `Ecs` for
+/// `begin_transaction`/`commit`/`undo`/`redo`.
+///
+/// Transactions nest: only committing the outermost one pushes it on the undo stack, and the
+/// inner ones are part of it.
+#[derive(Clone, Debug)]
+pub struct History<C, T> {
+    undo: Vec<Transaction<C, T>>,
+    redo: Vec<Transaction<C, T>>,
+    open: Option<Transaction<C, T>>,
+    depth: u32,
+    /// Set while undoing or redoing, whose mutations mustn't clear the redo stack.
+    replaying: bool,
+}
+
+impl<C, T> History<C, T> {
+    pub fn new() -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            open: None,
+            depth: 0,
+            replaying: false,
+        }
+    }
+
+    pub fn begin(&mut self) {
+        if self.depth == 0 {
+            self.open = Some(Transaction::new());
+        }
+        self.depth += 1;
+    }
+
+    /// Closes the innermost transaction. Returns `true` if that was the outermost one and it
+    /// changed something, so it can now be undone.
+    pub fn commit(&mut self) -> bool {
+        match self.depth {
+            0 => false,
+            1 => {
+                self.depth = 0;
+                let t = self.open.take().unwrap();
+                if t.is_empty() {
+                    return false;
+                }
+                self.undo.push(t);
+                self.redo.clear();
+                true
+            }
+            _ => {
+                self.depth -= 1;
+                false
+            }
+        }
+    }
+
+    /// Closes every open transaction, as if committing each of them.
+    pub fn commit_all(&mut self) -> bool {
+        self.depth = self.depth.min(1);
+        self.commit()
+    }
+
+    /// Closes every open transaction without pushing it on the undo stack, returning what it
+    /// recorded.
+    pub fn abort(&mut self) -> Option<Transaction<C, T>> {
+        self.depth = 0;
+        self.open.take()
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open.is_some()
+    }
+
+    /// How many transactions are open.
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// A point in the open transaction to roll back to with [`History::rollback_to`].
+    pub fn mark(&self) -> (usize, usize) {
+        self.open
+            .as_ref()
+            .map_or((0, 0), |t| (t.forward.len(), t.inverse.len()))
+    }
+
+    /// Forgets what the open transaction recorded since `mark`, returning the mutations that undo
+    /// it, to be applied in reverse order.
+    pub fn rollback_to(&mut self, mark: (usize, usize)) -> Vec<Mutation<C, T>> {
+        match &mut self.open {
+            Some(t) => {
+                t.forward.truncate(mark.0);
+                t.inverse.split_off(mark.1)
+            }
+            None => Vec::new(),
+        }
+    }
+
+    pub fn record(
+        &mut self,
+        forward: Mutation<C, T>,
+        inverse: impl IntoIterator<Item = Mutation<C, T>>,
+    ) {
+        if let Some(t) = &mut self.open {
+            t.forward.push(forward);
+            t.inverse.extend(inverse);
+        }
+    }
+
+    /// Notes a mutation made outside of any transaction. Unless it comes from undoing or redoing,
+    /// the redo stack no longer applies to the world and is cleared.
+    pub fn record_untracked(&mut self) {
+        if !self.replaying {
+            self.redo.clear();
+        }
+    }
+
+    pub fn set_replaying(&mut self, replaying: bool) {
+        self.replaying = replaying;
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn undo_len(&self) -> usize {
+        self.undo.len()
+    }
+
+    pub fn redo_len(&self) -> usize {
+        self.redo.len()
+    }
+
+    pub fn pop_undo(&mut self) -> Option<Transaction<C, T>> {
+        self.undo.pop()
+    }
+
+    pub fn pop_redo(&mut self) -> Option<Transaction<C, T>> {
+        self.redo.pop()
+    }
+
+    pub fn push_undo(&mut self, t: Transaction<C, T>) {
+        self.undo.push(t);
+    }
+
+    pub fn push_redo(&mut self, t: Transaction<C, T>) {
+        self.redo.push(t);
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+impl<C, T> Default for History<C, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,491 @@
+//! Looking into and editing a running game from an external tool.
+//!
+//! Only there with the `inspector` feature. [`Inspector`] serves a line-delimited JSON protocol
+//! on a local TCP socket. It does nothing on its own thread: the game calls [`Inspector::poll`]
+//! once per frame, which answers what clients asked since the last call and tells subscribers
+//! what changed.
+//!
+//! ```ignore
+//! let mut inspector = eliecs::inspector::Inspector::bind("127.0.0.1:7777")?;
+//! loop {
+//!     update(&mut ecs);
+//!     inspector.poll(&mut ecs)?;
+//! }
+//! ```
+//!
+//! Every request is one JSON object per line, with a `cmd` and an optional `id` that is echoed in
+//! the response. Responses are `{"id": .., "ok": ..}` or `{"id": .., "error": ".."}`. Entities are
+//! `[id, version]` and components are named as in `ComponentType::name`.
+//!
+//! | `cmd` | arguments | `ok` |
+//! |---|---|---|
+//! | `schema` | | `Ecs::schema()` |
+//! | `entities` | `query`: a text query, optional | the matching entities |
+//! | `components` | `entity` | `{"Position": {"x": 1.0, ..}, ..}` |
+//! | `get` | `entity`, `component`, `path` | the field's value |
+//! | `set` | `entity`, `component`, `path`, `value` | `null` |
+//! | `insert` | `entity`, `component`, `value` | `null` |
+//! | `remove` | `entity`, `component` | whether there was one |
+//! | `spawn` | `components`: like `components` returns, optional | the entity |
+//! | `despawn` | `entity` | whether it was alive |
+//! | `subscribe` | `components`: names, optional | `null` |
+//! | `unsubscribe` | | `null` |
+//!
+//! Subscribers get an event line every time something changed between two polls, found by
+//! diffing against a copy of the world kept while anyone is subscribed: `{"event": "spawned",
+//! "entity": ..}`, `{"event": "despawned", "entity": ..}`, `{"event": "changed", "entity": ..,
+//! "component": .., "value": ..}` and `{"event": "removed", "entity": .., "component": ..}`.
+//! Subscribing to some `components` leaves out changes to the others. Changes made by requests
+//! are reported in the same poll.
+//!
+//! Clients sending a line longer than 1 MiB, or not reading 16 MiB of replies and events, are
+//! disconnected. At most 64 requests per client are answered per poll; the rest wait for the
+//! next ones.
+
+use crate::{
+    reflect::{ReflectError, Value},
+    schema::Schema,
+    Entity, QueryError, WorldDelta,
+};
+
+pub use server::Inspector;
+
+/// A component's name and value.
+pub type NamedValue = (&'static str, Result<Value, ReflectError>);
+
+/// What changed between two states of a world, with components as [`NamedValue`]s.
+pub type Changes = WorldDelta<NamedValue, &'static str>;
+
+/// Implemented by the `Ecs` generated by `components!`, with components named as in
+/// `ComponentType::name`. Methods taking a name fail if there is no such component.
+pub trait Inspect: Clone {
+    fn schema() -> Schema;
+
+    /// The name `ComponentType::name` gives the component called `name`, which can also be
+    /// spelled like the type or in snake case.
+    fn component_name(name: &str) -> Option<&'static str>;
+
+    /// Every alive entity if `query` is `None`, else the ones matching the text query.
+    fn find(&self, query: Option<&str>) -> Result<Vec<Entity>, QueryError>;
+
+    /// The components of `e`, or `None` if it is dead.
+    fn component_values(&self, e: Entity) -> Option<Vec<NamedValue>>;
+
+    fn field_value(&self, e: Entity, component: &str, path: &str) -> Result<Value, ReflectError>;
+
+    fn set_field_value(
+        &mut self,
+        e: Entity,
+        component: &str,
+        path: &str,
+        value: Value,
+    ) -> Result<(), ReflectError>;
+
+    /// Inserts a component into `e`, replacing the one it had.
+    fn insert_value(
+        &mut self,
+        e: Entity,
+        component: &str,
+        value: Value,
+    ) -> Result<(), ReflectError>;
+
+    /// Removes a component from `e`, returning whether it had one.
+    fn remove_named(&mut self, e: Entity, component: &str) -> Result<bool, ReflectError>;
+
+    /// Spawns an entity with `components`, or nothing if one of them is wrong.
+    fn spawn_values(&mut self, components: Vec<(String, Value)>) -> Result<Entity, ReflectError>;
+
+    /// Despawns `e`, returning whether it was alive.
+    fn despawn_entity(&mut self, e: Entity) -> bool;
+
+    /// What changed from `old` to `self`.
+    fn changes(&self, old: &Self) -> Changes;
+}
+
+mod server {
+    use std::{
+        collections::{BTreeMap, BTreeSet},
+        io::{self, Read, Write},
+        net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    };
+
+    use serde::Deserialize;
+    use serde_json::{json, Value as Json};
+
+    use super::Inspect;
+    use crate::{
+        reflect::{ReflectError, Value},
+        Entity,
+    };
+
+    #[derive(Deserialize)]
+    #[serde(tag = "cmd", rename_all = "snake_case", deny_unknown_fields)]
+    enum Command {
+        Schema,
+        Entities {
+            #[serde(default)]
+            query: Option<String>,
+        },
+        Components {
+            entity: Entity,
+        },
+        Get {
+            entity: Entity,
+            component: String,
+            path: String,
+        },
+        Set {
+            entity: Entity,
+            component: String,
+            path: String,
+            value: Value,
+        },
+        Insert {
+            entity: Entity,
+            component: String,
+            value: Value,
+        },
+        Remove {
+            entity: Entity,
+            component: String,
+        },
+        Spawn {
+            #[serde(default)]
+            components: BTreeMap<String, Value>,
+        },
+        Despawn {
+            entity: Entity,
+        },
+        Subscribe {
+            #[serde(default)]
+            components: Option<BTreeSet<String>>,
+        },
+        Unsubscribe,
+    }
+
+    /// Longest request line a client may send, so one can't make us buffer without end.
+    const MAX_LINE: usize = 1 << 20;
+    /// Most requests answered per client and poll, so one can't stall the game.
+    const MAX_LINES_PER_POLL: usize = 64;
+    /// Most bytes kept for a client that doesn't read them.
+    const MAX_OUTGOING: usize = 1 << 24;
+
+    struct Client {
+        stream: TcpStream,
+        /// Received bytes that don't make a full line yet.
+        incoming: Vec<u8>,
+        /// Bytes the socket hasn't taken yet.
+        outgoing: Vec<u8>,
+        /// `Some` while subscribed, with the components it is limited to.
+        subscription: Option<Option<BTreeSet<&'static str>>>,
+        closed: bool,
+    }
+
+    impl Client {
+        /// Queues `message`, or closes the connection if more than [`MAX_OUTGOING`] bytes would be
+        /// waiting.
+        fn send(&mut self, message: &Json) {
+            if self.closed {
+                return;
+            }
+            serde_json::to_writer(&mut self.outgoing, message).expect("writing JSON to a Vec");
+            self.outgoing.push(b'\n');
+            if self.outgoing.len() > MAX_OUTGOING {
+                self.closed = true;
+                self.outgoing = Vec::new();
+            }
+        }
+
+        fn wants(&self, component: &str) -> bool {
+            match &self.subscription {
+                Some(Some(components)) => components.contains(component),
+                Some(None) => true,
+                None => false,
+            }
+        }
+
+        /// Up to [`MAX_LINES_PER_POLL`] full lines, leaving later ones for the next call. Closes
+        /// the connection if one is longer than [`MAX_LINE`].
+        fn read(&mut self) -> Vec<Vec<u8>> {
+            let mut buf = [0; 4096];
+            let mut lines = Vec::new();
+            loop {
+                while lines.len() < MAX_LINES_PER_POLL {
+                    let Some(end) = self.incoming.iter().position(|&b| b == b'\n') else {
+                        break;
+                    };
+                    // `end` doesn't count the newline
+                    if end > MAX_LINE {
+                        self.closed = true;
+                        return Vec::new();
+                    }
+                    lines.push(self.incoming.drain(..=end).collect::<Vec<_>>());
+                }
+                if lines.len() == MAX_LINES_PER_POLL {
+                    break;
+                }
+                if self.incoming.len() > MAX_LINE {
+                    self.closed = true;
+                    return Vec::new();
+                }
+                match self.stream.read(&mut buf) {
+                    Ok(0) => {
+                        self.closed = true;
+                        break;
+                    }
+                    Ok(n) => self.incoming.extend_from_slice(&buf[..n]),
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    Err(_) => {
+                        self.closed = true;
+                        break;
+                    }
+                }
+            }
+            lines
+        }
+
+        fn flush(&mut self) {
+            while !self.outgoing.is_empty() {
+                match self.stream.write(&self.outgoing) {
+                    Ok(0) => {
+                        self.closed = true;
+                        return;
+                    }
+                    Ok(n) => {
+                        self.outgoing.drain(..n);
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => return,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    Err(_) => {
+                        self.closed = true;
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Serves the protocol described in the [module docs](super) for a world `W`.
+    pub struct Inspector<W> {
+        listener: TcpListener,
+        clients: Vec<Client>,
+        /// The world as of the last poll, kept while anyone is subscribed.
+        last: Option<W>,
+    }
+
+    impl<W: Inspect> Inspector<W> {
+        /// Listens on `addr`, e.g. `"127.0.0.1:7777"`. Anyone who can connect can edit the world,
+        /// so this shouldn't be reachable from outside the machine.
+        pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+            let listener = TcpListener::bind(addr)?;
+            listener.set_nonblocking(true)?;
+            Ok(Self {
+                listener,
+                clients: Vec::new(),
+                last: None,
+            })
+        }
+
+        pub fn local_addr(&self) -> io::Result<SocketAddr> {
+            self.listener.local_addr()
+        }
+
+        /// How many clients are connected.
+        pub fn clients(&self) -> usize {
+            self.clients.len()
+        }
+
+        /// Accepts new clients, answers requests and tells subscribers what changed since the
+        /// last poll, including through those requests. Never blocks; replies the socket can't
+        /// take yet are sent on later polls. Fails only if accepting fails, problems with one
+        /// client disconnect it.
+        pub fn poll(&mut self, world: &mut W) -> io::Result<()> {
+            loop {
+                match self.listener.accept() {
+                    Ok((stream, _)) => {
+                        stream.set_nonblocking(true)?;
+                        stream.set_nodelay(true)?;
+                        self.clients.push(Client {
+                            stream,
+                            incoming: Vec::new(),
+                            outgoing: Vec::new(),
+                            subscription: None,
+                            closed: false,
+                        });
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                    Err(e) => return Err(e),
+                }
+            }
+
+            for client in &mut self.clients {
+                for line in client.read() {
+                    if line.trim_ascii().is_empty() {
+                        continue;
+                    }
+                    let reply = match serde_json::from_slice::<Json>(&line) {
+                        Ok(mut request) => {
+                            let id = request
+                                .as_object_mut()
+                                .and_then(|request| request.remove("id"))
+                                .unwrap_or(Json::Null);
+                            match Command::deserialize(request)
+                                .map_err(|e| e.to_string())
+                                .and_then(|command| run(client, world, command))
+                            {
+                                Ok(ok) => json!({ "id": id, "ok": ok }),
+                                Err(error) => json!({ "id": id, "error": error }),
+                            }
+                        }
+                        Err(e) => json!({ "id": null, "error": e.to_string() }),
+                    };
+                    client.send(&reply);
+                }
+            }
+
+            if let Some(last) = &self.last {
+                self.notify(&world.changes(last));
+            }
+            for client in &mut self.clients {
+                client.flush();
+            }
+            self.clients.retain(|client| !client.closed);
+
+            if self
+                .clients
+                .iter()
+                .any(|client| client.subscription.is_some())
+            {
+                match &mut self.last {
+                    Some(last) => last.clone_from(world),
+                    None => self.last = Some(world.clone()),
+                }
+            } else {
+                self.last = None;
+            }
+            Ok(())
+        }
+
+        fn notify(&mut self, changes: &super::Changes) {
+            for client in &mut self.clients {
+                if client.subscription.is_none() {
+                    continue;
+                }
+                for e in &changes.spawned {
+                    client.send(&json!({ "event": "spawned", "entity": e }));
+                }
+                for e in &changes.despawned {
+                    client.send(&json!({ "event": "despawned", "entity": e }));
+                }
+                for (e, (component, value)) in changes.inserted.iter().chain(&changes.updated) {
+                    if client.wants(component) {
+                        client.send(&json!({
+                            "event": "changed",
+                            "entity": e,
+                            "component": component,
+                            "value": value_json(value),
+                        }));
+                    }
+                }
+                for (e, component) in &changes.removed {
+                    if client.wants(component) {
+                        client.send(&json!({
+                            "event": "removed",
+                            "entity": e,
+                            "component": component,
+                        }));
+                    }
+                }
+            }
+        }
+    }
+
+    fn run<W: Inspect>(
+        client: &mut Client,
+        world: &mut W,
+        command: Command,
+    ) -> Result<Json, String> {
+        let ok = match command {
+            Command::Schema => json!(W::schema()),
+            Command::Entities { query } => {
+                json!(world.find(query.as_deref()).map_err(|e| e.message)?)
+            }
+            Command::Components { entity } => {
+                let components = world
+                    .component_values(entity)
+                    .ok_or_else(|| format!("{entity:?} isn't alive"))?;
+                Json::Object(
+                    components
+                        .iter()
+                        .map(|(name, value)| (name.to_string(), value_json(value)))
+                        .collect(),
+                )
+            }
+            Command::Get {
+                entity,
+                component,
+                path,
+            } => json!(world
+                .field_value(entity, &component, &path)
+                .map_err(|e| e.0)?),
+            Command::Set {
+                entity,
+                component,
+                path,
+                value,
+            } => {
+                world
+                    .set_field_value(entity, &component, &path, value)
+                    .map_err(|e| e.0)?;
+                Json::Null
+            }
+            Command::Insert {
+                entity,
+                component,
+                value,
+            } => {
+                world
+                    .insert_value(entity, &component, value)
+                    .map_err(|e| e.0)?;
+                Json::Null
+            }
+            Command::Remove { entity, component } => {
+                json!(world.remove_named(entity, &component).map_err(|e| e.0)?)
+            }
+            Command::Spawn { components } => {
+                json!(world
+                    .spawn_values(components.into_iter().collect())
+                    .map_err(|e| e.0)?)
+            }
+            Command::Despawn { entity } => json!(world.despawn_entity(entity)),
+            Command::Subscribe { components } => {
+                let components = components
+                    .map(|names| {
+                        names
+                            .iter()
+                            .map(|name| {
+                                W::component_name(name)
+                                    .ok_or_else(|| format!("no component `{name}`"))
+                            })
+                            .collect::<Result<_, _>>()
+                    })
+                    .transpose()?;
+                client.subscription = Some(components);
+                Json::Null
+            }
+            Command::Unsubscribe => {
+                client.subscription = None;
+                Json::Null
+            }
+        };
+        Ok(ok)
+    }
+
+    fn value_json(value: &Result<Value, ReflectError>) -> Json {
+        match value {
+            Ok(value) => json!(value),
+            Err(e) => json!({ "error": e.0 }),
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use crate::Entity;
+
+/// One recorded change to a world. `C` is the generated `ComponentTypeContaining` and `T` the
+/// generated `ComponentType`.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum Mutation<C, T> {
+    Spawn(Entity),
+    Despawn(Entity),
+    /// Adding a component or overwriting it, including writes through `*_mut` when the journal
+    /// tracks writes.
+    Insert(Entity, C),
+    Remove(Entity, T),
+}
+
+/// Every mutation of an `Ecs` since `Ecs::start_journal`, in order. Replaying it with
+/// `Ecs::replay` on a snapshot taken when the journal was started reconstructs the world at any
+/// step.
+///
+/// Writes through `*_mut` and `query_*_mut` can't be seen as they happen; when tracking them the
+/// `Ecs` keeps the old value and records an `Insert` if it changed by the time the next mutation
+/// is recorded, or `Ecs::flush_journal` is called. That is also when it stops looking, so a `&mut`
+/// mustn't be written through past the next mutation: writes made after it are missed, take a
+/// new `&mut` instead.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Journal<C, T> {
+    pub records: Vec<Mutation<C, T>>,
+    #[serde(skip)]
+    track_writes: bool,
+}
+
+impl<C, T> Journal<C, T> {
+    pub fn new(track_writes: bool) -> Self {
+        Self {
+            records: Vec::new(),
+            track_writes,
+        }
+    }
+
+    pub fn tracks_writes(&self) -> bool {
+        self.track_writes
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    #[doc(hidden)]
+    pub fn record(&mut self, m: Mutation<C, T>) {
+        self.records.push(m);
+    }
+}
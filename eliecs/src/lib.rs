@@ -1,14 +1,47 @@
+mod borrow;
+mod delta;
+mod entity_map;
+mod error;
+pub mod hash;
+mod history;
+#[cfg(feature = "inspector")]
+pub mod inspector;
+mod journal;
 mod pool;
+mod prefab;
+mod query;
+pub mod reflect;
+pub mod replication;
+pub mod save;
+mod scene;
+pub mod schema;
+mod snapshot;
+mod stats;
+mod table;
+mod text_query;
 
 use std::{
     fmt::Debug,
     num::{NonZeroU32, NonZeroU64},
 };
 
-pub use pool::Pool;
+pub use borrow::{BorrowFlags, ComponentRef, ComponentRefMut};
+pub use delta::WorldDelta;
+pub use entity_map::{EntityMap, MapEntities};
+pub use error::EcsError;
+pub use history::{History, Transaction};
+pub use journal::{Journal, Mutation};
+pub use pool::{Pool, PoolStats};
+pub use prefab::{Prefab, PrefabError, PrefabInstance, Prefabs, Template};
+pub use query::DynQuery;
+pub use scene::{Scene, SceneEntity};
+pub use snapshot::SnapshotRing;
+pub use stats::EcsStats;
+pub use table::Table;
+pub use text_query::{CompareOp, Condition, QueryError, TextQuery};
 
 pub use eliecs_macros::components;
-use serde::{de::Visitor, ser::SerializeTuple};
+use serde::{de::Visitor, ser::SerializeTupleStruct};
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Entity {
@@ -17,6 +50,9 @@ pub struct Entity {
 }
 
 impl Entity {
+    /// Never alive: ids are handed out counting up from 0, which can't get to `u32::MAX`.
+    pub const DANGLING: Entity = Entity::new(u32::MAX, NonZeroU32::MAX);
+
     pub const fn new(id: u32, version: NonZeroU32) -> Self {
         Self { id, version }
     }
@@ -40,9 +76,10 @@ impl serde::Serialize for Entity {
     where
         S: serde::Serializer,
     {
-        let mut tup = serializer.serialize_tuple(2)?;
-        tup.serialize_element(&self.id)?;
-        tup.serialize_element(&self.version)?;
+        // a tuple struct rather than a tuple so `reflect` can tell entities from other pairs
+        let mut tup = serializer.serialize_tuple_struct("Entity", 2)?;
+        tup.serialize_field(&self.id)?;
+        tup.serialize_field(&self.version)?;
         tup.end()
     }
 }
@@ -73,7 +110,7 @@ impl<'de> serde::Deserialize<'de> for Entity {
                 formatter.write_str("a tuple of (u32 id, u32 version)")
             }
         }
-        deserializer.deserialize_tuple(2, EntityVisitor)
+        deserializer.deserialize_tuple_struct("Entity", 2, EntityVisitor)
     }
 }
 
@@ -83,6 +120,22 @@ impl Debug for Entity {
     }
 }
 
+/// Emits its input only with the `inspector` feature, for `components!` to implement
+/// `inspector::Inspect`: a `cfg` in the generated code would check the user's features instead.
+#[cfg(feature = "inspector")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __if_inspector {
+    ($($tokens:tt)*) => { $($tokens)* };
+}
+
+#[cfg(not(feature = "inspector"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __if_inspector {
+    ($($tokens:tt)*) => {};
+}
+
 #[cfg(test)]
 mod tests {
     use crate::Pool;
@@ -138,4 +191,41 @@ mod tests {
         assert!(!pool.contains(5));
         assert!(pool.contains(7));
     }
+
+    #[test]
+    fn take() {
+        let mut pool = Pool::<u32>::new();
+        pool.insert(2, 1234);
+        pool.insert(5, 5678);
+        pool.insert(7, 91011);
+        assert_eq!(pool.take(2), Some(1234));
+        assert_eq!(pool.take(2), None);
+        assert_eq!(pool.get(5).copied(), Some(5678));
+        assert_eq!(pool.get(7).copied(), Some(91011));
+        assert_eq!(pool.take(7), Some(91011));
+        assert_eq!(pool.len(), 1);
+    }
+
+    #[test]
+    fn insert_overwrites() {
+        let mut pool = Pool::<u32>::new();
+        assert!(!pool.insert(3, 1));
+        assert!(pool.insert(3, 2));
+        assert_eq!(pool.get(3).copied(), Some(2));
+        assert_eq!(pool.len(), 1);
+        assert_eq!(pool.iter().count(), 1);
+    }
+
+    #[test]
+    fn eq_ignores_dense_order() {
+        let mut a = Pool::<u32>::new();
+        a.insert(1, 10);
+        a.insert(2, 20);
+        let mut b = Pool::<u32>::new();
+        b.insert(2, 20);
+        b.insert(1, 10);
+        assert_eq!(a, b);
+        b.insert(1, 11);
+        assert_ne!(a, b);
+    }
 }
@@ -4,7 +4,7 @@ use serde::{de::Visitor, ser::SerializeTupleStruct};
 
 type Index = u32;
 
-#[derive(Clone, Debug)]
+#[derive(Debug)]
 pub struct Pool<T> {
     sparse: Vec<Index>,
     dense: Vec<(Index, T)>,
@@ -33,7 +33,10 @@ impl<T> Pool<T> {
     }
 
     pub fn insert(&mut self, i: Index, v: T) -> bool {
-        let already_existed = self.contains(i);
+        if let Some(old) = self.get_mut(i) {
+            *old = v;
+            return true;
+        }
         let dense_idx = self.dense.len() as Index;
         if self.sparse.len() < (i as usize + 1) {
             // fills new empty space with u32::MAX
@@ -41,7 +44,7 @@ impl<T> Pool<T> {
         }
         self.sparse[i as usize] = dense_idx;
         self.dense.push((i, v));
-        already_existed
+        false
     }
 
     pub fn get(&self, i: Index) -> Option<&T> {
@@ -69,18 +72,22 @@ impl<T> Pool<T> {
     }
 
     pub fn remove(&mut self, i: Index) -> bool {
+        self.take(i).is_some()
+    }
+
+    /// Removes the value at `i` and returns it.
+    pub fn take(&mut self, i: Index) -> Option<T> {
         if !self.contains(i) {
-            return false;
+            return None;
         }
 
-        let tail_dense = self.dense.pop().unwrap();
-        let tail_sparse_ref = tail_dense.0;
-        if (self.sparse[i as usize] as usize) < self.dense.len() {
-            self.dense[self.sparse[i as usize] as usize] = tail_dense;
+        let dense_idx = self.sparse[i as usize] as usize;
+        let (_, v) = self.dense.swap_remove(dense_idx);
+        if let Some(&(moved, _)) = self.dense.get(dense_idx) {
+            self.sparse[moved as usize] = dense_idx as Index;
         }
-        self.sparse[tail_sparse_ref as usize] = self.sparse[i as usize];
 
-        true
+        Some(v)
     }
 
     pub fn clear(&mut self) {
@@ -103,6 +110,81 @@ impl<T> Pool<T> {
     pub fn is_empty(&self) -> bool {
         self.dense.is_empty()
     }
+
+    pub fn memory_stats(&self) -> PoolStats {
+        PoolStats {
+            sparse_len: self.sparse.len(),
+            sparse_capacity: self.sparse.capacity(),
+            dense_len: self.dense.len(),
+            dense_capacity: self.dense.capacity(),
+            bytes: self.sparse.capacity() * std::mem::size_of::<Index>()
+                + self.dense.capacity() * std::mem::size_of::<(Index, T)>(),
+        }
+    }
+
+    /// Frees the capacity the arrays don't use, and the end of the sparse array past the highest
+    /// index that has a value.
+    pub fn shrink_to_fit(&mut self) {
+        let sparse_len = self
+            .dense
+            .iter()
+            .map(|&(i, _)| i as usize + 1)
+            .max()
+            .unwrap_or(0);
+        self.sparse.truncate(sparse_len);
+        self.sparse.shrink_to_fit();
+        self.dense.shrink_to_fit();
+    }
+
+    /// The arrays as they are serialized, for reading saves without the component types.
+    pub(crate) fn from_parts(sparse: Vec<Index>, dense: Vec<(Index, T)>) -> Self {
+        Self { sparse, dense }
+    }
+
+    pub(crate) fn parts(&self) -> (&[Index], &[(Index, T)]) {
+        (&self.sparse, &self.dense)
+    }
+
+    /// Whether every value's index points back at it. Only pools read from a corrupted save
+    /// aren't.
+    pub(crate) fn is_consistent(&self) -> bool {
+        self.dense
+            .iter()
+            .enumerate()
+            .all(|(i, &(index, _))| self.sparse.get(index as usize) == Some(&(i as Index)))
+    }
+}
+
+impl<T: Clone> Clone for Pool<T> {
+    fn clone(&self) -> Self {
+        Self {
+            sparse: self.sparse.clone(),
+            dense: self.dense.clone(),
+        }
+    }
+
+    // keeps our buffers, so snapshotting into the same pool every frame doesn't allocate
+    fn clone_from(&mut self, source: &Self) {
+        self.sparse.clone_from(&source.sparse);
+        self.dense.clone_from(&source.dense);
+    }
+}
+
+/// Pools are equal when they hold the same values at the same indices, regardless of the order of
+/// the dense array.
+impl<T: PartialEq> PartialEq for Pool<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|(i, v)| other.get(i) == Some(v))
+    }
+}
+
+impl<T> IntoIterator for Pool<T> {
+    type Item = (Index, T);
+    type IntoIter = std::vec::IntoIter<(Index, T)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.dense.into_iter()
+    }
 }
 
 impl<T> serde::Serialize for Pool<T>
@@ -161,3 +243,48 @@ impl<T> Default for Pool<T> {
         Self::new()
     }
 }
+
+/// How much memory a [`Pool`] has allocated, see [`Pool::memory_stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Slots in the sparse array, one per index up to the highest one ever inserted.
+    pub sparse_len: usize,
+    pub sparse_capacity: usize,
+    /// Values in the pool.
+    pub dense_len: usize,
+    pub dense_capacity: usize,
+    /// Bytes allocated for both arrays. Memory the values own themselves, like the buffer of a
+    /// `String`, isn't counted.
+    pub bytes: usize,
+}
+
+impl PoolStats {
+    /// The share of sparse slots that point at a value, low after despawning most entities.
+    pub fn occupancy(&self) -> f64 {
+        if self.sparse_len == 0 {
+            1.0
+        } else {
+            self.dense_len as f64 / self.sparse_len as f64
+        }
+    }
+}
+
+impl std::ops::Add for PoolStats {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            sparse_len: self.sparse_len + other.sparse_len,
+            sparse_capacity: self.sparse_capacity + other.sparse_capacity,
+            dense_len: self.dense_len + other.dense_len,
+            dense_capacity: self.dense_capacity + other.dense_capacity,
+            bytes: self.bytes + other.bytes,
+        }
+    }
+}
+
+impl std::iter::Sum for PoolStats {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), std::ops::Add::add)
+    }
+}
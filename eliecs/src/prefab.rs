@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+
+use crate::Entity;
+
+/// Implemented by the generated `FatEntity` so prefabs can override their parent's components.
+pub trait Template: Clone {
+    /// `self` with every component `overrides` has replaced.
+    fn merge(self, overrides: Self) -> Self;
+}
+
+/// A named entity template, registered in [`Prefabs`].
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Prefab<F> {
+    /// The prefab this one starts from; `template` overrides its components.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    pub template: F,
+    /// Prefabs spawned along with this one, after the ones its parent has, see
+    /// [`PrefabInstance`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<String>,
+}
+
+impl<F> Prefab<F> {
+    pub fn new(template: F) -> Self {
+        Self {
+            parent: None,
+            template,
+            children: Vec::new(),
+        }
+    }
+
+    pub fn inherit(parent: impl Into<String>, template: F) -> Self {
+        Self {
+            parent: Some(parent.into()),
+            template,
+            children: Vec::new(),
+        }
+    }
+
+    pub fn child(mut self, name: impl Into<String>) -> Self {
+        self.children.push(name.into());
+        self
+    }
+}
+
+/// The entities spawned for a prefab and, recursively, its children.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PrefabInstance {
+    pub root: Entity,
+    pub children: Vec<PrefabInstance>,
+}
+
+impl PrefabInstance {
+    /// Every spawned entity, the root first.
+    pub fn entities(&self) -> Vec<Entity> {
+        let mut entities = vec![self.root];
+        for child in &self.children {
+            entities.extend(child.entities());
+        }
+        entities
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PrefabError {
+    /// No prefab is registered under this name.
+    Unknown(String),
+    /// The prefab is its own ancestor or descendant.
+    Cycle(String),
+}
+
+impl std::fmt::Display for PrefabError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PrefabError::Unknown(name) => write!(f, "no prefab named {name:?}"),
+            PrefabError::Cycle(name) => write!(f, "prefab {name:?} contains itself"),
+        }
+    }
+}
+
+impl std::error::Error for PrefabError {}
+
+/// Prefabs by name. Serializes as a map, so a whole registry can be loaded from a file:
+///
+/// ```json
+/// {
+///     "orc": { "template": { "health": { "hp": 10 } } },
+///     "orc_archer": { "parent": "orc", "template": { "name": "archer" }, "children": ["bow"] },
+///     "bow": { "template": { "name": "bow" } }
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct Prefabs<F> {
+    prefabs: HashMap<String, Prefab<F>>,
+}
+
+impl<F> Prefabs<F> {
+    pub fn new() -> Self {
+        Self {
+            prefabs: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, name: impl Into<String>, prefab: Prefab<F>) -> Option<Prefab<F>> {
+        self.prefabs.insert(name.into(), prefab)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Prefab<F>> {
+        self.prefabs.get(name)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Prefab<F>> {
+        self.prefabs.remove(name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.prefabs.contains_key(name)
+    }
+
+    /// Adds every prefab of `other`, replacing ones with the same name.
+    pub fn extend(&mut self, other: Prefabs<F>) {
+        self.prefabs.extend(other.prefabs);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Prefab<F>)> {
+        self.prefabs.iter().map(|(k, v)| (k.as_str(), v))
+    }
+
+    pub fn len(&self) -> usize {
+        self.prefabs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prefabs.is_empty()
+    }
+
+    /// The children `name` and its parents have, the furthest parent's first.
+    pub fn children(&self, name: &str) -> Result<Vec<&str>, PrefabError> {
+        Ok(self
+            .chain(name)?
+            .into_iter()
+            .rev()
+            .flat_map(|prefab| prefab.children.iter().map(String::as_str))
+            .collect())
+    }
+
+    /// `name` and its parents, `name` first.
+    fn chain(&self, name: &str) -> Result<Vec<&Prefab<F>>, PrefabError> {
+        let mut chain = Vec::<(&str, _)>::new();
+        let mut next = Some(name);
+        while let Some(name) = next {
+            if chain.iter().any(|(n, _)| *n == name) {
+                return Err(PrefabError::Cycle(name.to_string()));
+            }
+            let prefab = self
+                .get(name)
+                .ok_or_else(|| PrefabError::Unknown(name.to_string()))?;
+            chain.push((name, prefab));
+            next = prefab.parent.as_deref();
+        }
+        Ok(chain.into_iter().map(|(_, prefab)| prefab).collect())
+    }
+}
+
+impl<F: Template> Prefabs<F> {
+    /// The template of `name` with its parents' components filled in.
+    pub fn resolve(&self, name: &str) -> Result<F, PrefabError> {
+        let mut chain = self.chain(name)?;
+        let root = chain.pop().unwrap();
+        Ok(chain
+            .into_iter()
+            .rev()
+            .fold(root.template.clone(), |template, prefab| {
+                template.merge(prefab.template.clone())
+            }))
+    }
+
+    /// Checks that `name`, its parents and all of its children exist and that none of them
+    /// contain themselves, so spawning it can't fail halfway.
+    pub fn validate(&self, name: &str) -> Result<(), PrefabError> {
+        self.validate_tree(name, &mut Vec::new())
+    }
+
+    fn validate_tree<'a>(
+        &'a self,
+        name: &'a str,
+        stack: &mut Vec<&'a str>,
+    ) -> Result<(), PrefabError> {
+        if stack.contains(&name) {
+            return Err(PrefabError::Cycle(name.to_string()));
+        }
+        self.resolve(name)?;
+        stack.push(name);
+        for child in self.children(name)? {
+            self.validate_tree(child, stack)?;
+        }
+        stack.pop();
+        Ok(())
+    }
+}
+
+impl<F> Default for Prefabs<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,53 @@
+/// A query put together at runtime, e.g. from a debug console: the entities that have every
+/// component in `include` and none in `exclude`. `T` is the generated `ComponentType`; run it with
+/// the generated `Ecs::query_dyn`.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DynQuery<T> {
+    include: Vec<T>,
+    exclude: Vec<T>,
+}
+
+impl<T: Copy + PartialEq> DynQuery<T> {
+    pub fn new() -> Self {
+        Self {
+            include: Vec::new(),
+            exclude: Vec::new(),
+        }
+    }
+
+    /// Only matches entities that have `ty`.
+    pub fn with(mut self, ty: T) -> Self {
+        if !self.include.contains(&ty) {
+            self.include.push(ty);
+        }
+        self
+    }
+
+    /// Only matches entities that don't have `ty`.
+    pub fn without(mut self, ty: T) -> Self {
+        if !self.exclude.contains(&ty) {
+            self.exclude.push(ty);
+        }
+        self
+    }
+
+    /// The types matched entities have, in the order they were added.
+    pub fn include(&self) -> &[T] {
+        &self.include
+    }
+
+    pub fn exclude(&self) -> &[T] {
+        &self.exclude
+    }
+
+    /// Whether an entity with the components `has` says it has matches.
+    pub fn matches(&self, mut has: impl FnMut(T) -> bool) -> bool {
+        self.include.iter().all(|&ty| has(ty)) && !self.exclude.iter().any(|&ty| has(ty))
+    }
+}
+
+impl<T: Copy + PartialEq> Default for DynQuery<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
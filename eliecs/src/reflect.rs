@@ -0,0 +1,792 @@
+//! Looking into components at runtime, for consoles and tools that don't know the component
+//! types.
+//!
+//! Components are turned into [`Value`]s through their `Serialize` impl and back through their
+//! `Deserialize` impl, so any component `components!` accepts can be reflected. On top of that,
+//! `components!` implements [`Reflect`] for every component, which describes its fields and gets
+//! and sets them by name.
+
+use serde::{
+    de::{self, IntoDeserializer},
+    ser, Deserialize, Serialize,
+};
+
+use crate::Entity;
+
+/// A component, or part of one, as seen through its `Serialize` impl.
+///
+/// Newtype structs and `Some` are transparent: `CName(String)` becomes a [`Value::String`] and
+/// `Some(1)` a [`Value::Int`].
+///
+/// Values serialize as what they hold, e.g. `{"x": 1.0}` rather than `{"Struct": ..}`, so they
+/// read naturally in JSON. Deserializing one back can't tell entities from lists or structs from
+/// maps and variants, but [`from_value`] accepts either.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    /// `None` and `()`.
+    None,
+    Bool(bool),
+    /// Every integer type; only `u128`s above `i128::MAX` don't fit.
+    Int(i128),
+    /// `f32` and `f64`.
+    Float(f64),
+    /// Strings and chars.
+    String(String),
+    Entity(Entity),
+    /// Sequences, tuples and tuple structs.
+    List(Vec<Value>),
+    /// Structs, by field name in declaration order.
+    Struct(Vec<(String, Value)>),
+    Map(Vec<(Value, Value)>),
+    /// An enum variant and its content, [`Value::None`] for unit variants.
+    Variant(String, Box<Value>),
+}
+
+impl Value {
+    /// The struct field, list element or map value `name` refers to. Variants are looked into, so
+    /// `Some(Shape::Circle { radius })` has a `radius` field.
+    pub fn field(&self, name: &str) -> Option<&Value> {
+        match self {
+            Value::Struct(fields) => fields.iter().find(|(n, _)| n == name).map(|(_, v)| v),
+            Value::List(values) => values.get(name.parse::<usize>().ok()?),
+            Value::Map(entries) => entries
+                .iter()
+                .find(|(k, _)| match k {
+                    Value::String(k) => k == name,
+                    Value::Int(k) => name.parse() == Ok(*k),
+                    _ => false,
+                })
+                .map(|(_, v)| v),
+            Value::Variant(_, content) => content.field(name),
+            _ => None,
+        }
+    }
+
+    pub fn field_mut(&mut self, name: &str) -> Option<&mut Value> {
+        match self {
+            Value::Struct(fields) => fields.iter_mut().find(|(n, _)| n == name).map(|(_, v)| v),
+            Value::List(values) => values.get_mut(name.parse::<usize>().ok()?),
+            Value::Map(entries) => entries
+                .iter_mut()
+                .find(|(k, _)| match k {
+                    Value::String(k) => k == name,
+                    Value::Int(k) => name.parse() == Ok(*k),
+                    _ => false,
+                })
+                .map(|(_, v)| v),
+            Value::Variant(_, content) => content.field_mut(name),
+            _ => None,
+        }
+    }
+
+    /// Follows [`Value::field`] down `path`.
+    pub fn path<'a>(&self, path: impl IntoIterator<Item = &'a str>) -> Option<&Value> {
+        path.into_iter()
+            .try_fold(self, |value, name| value.field(name))
+    }
+
+    pub fn path_mut<'a>(&mut self, path: impl IntoIterator<Item = &'a str>) -> Option<&mut Value> {
+        path.into_iter()
+            .try_fold(self, |value, name| value.field_mut(name))
+    }
+
+    /// What kind of value this is, for error messages.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Value::None => "none",
+            Value::Bool(_) => "bool",
+            Value::Int(_) => "integer",
+            Value::Float(_) => "float",
+            Value::String(_) => "string",
+            Value::Entity(_) => "entity",
+            Value::List(_) => "list",
+            Value::Struct(_) => "struct",
+            Value::Map(_) => "map",
+            Value::Variant(..) => "enum",
+        }
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use ser::{SerializeMap, SerializeSeq};
+        match self {
+            Value::None => serializer.serialize_unit(),
+            Value::Bool(v) => serializer.serialize_bool(*v),
+            // the smallest type that fits, since not every format has 128 bit integers
+            Value::Int(v) => match (i64::try_from(*v), u64::try_from(*v)) {
+                (Ok(v), _) => serializer.serialize_i64(v),
+                (_, Ok(v)) => serializer.serialize_u64(v),
+                _ => serializer.serialize_i128(*v),
+            },
+            Value::Float(v) => serializer.serialize_f64(*v),
+            Value::String(v) => serializer.serialize_str(v),
+            Value::Entity(e) => e.serialize(serializer),
+            Value::List(values) => {
+                let mut seq = serializer.serialize_seq(Some(values.len()))?;
+                for v in values {
+                    seq.serialize_element(v)?;
+                }
+                seq.end()
+            }
+            Value::Struct(fields) => {
+                let mut map = serializer.serialize_map(Some(fields.len()))?;
+                for (k, v) in fields {
+                    map.serialize_entry(k, v)?;
+                }
+                map.end()
+            }
+            Value::Map(entries) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (k, v) in entries {
+                    map.serialize_entry(k, v)?;
+                }
+                map.end()
+            }
+            Value::Variant(variant, content) if **content == Value::None => {
+                serializer.serialize_str(variant)
+            }
+            Value::Variant(variant, content) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(variant, content)?;
+                map.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+/// Builds a [`Value`] out of whatever the deserializer has.
+pub(crate) struct ValueVisitor;
+
+impl<'de> de::Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("any value")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::Int(v.into()))
+    }
+    fn visit_i128<E: de::Error>(self, v: i128) -> Result<Value, E> {
+        Ok(Value::Int(v))
+    }
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Value, E> {
+        Ok(Value::Int(v.into()))
+    }
+    fn visit_u128<E: de::Error>(self, v: u128) -> Result<Value, E> {
+        int(v).map_err(|e| E::custom(e))
+    }
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Value, E> {
+        Ok(Value::Float(v))
+    }
+    fn visit_char<E: de::Error>(self, v: char) -> Result<Value, E> {
+        Ok(Value::String(v.to_string()))
+    }
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::String(v.to_string()))
+    }
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Value, E> {
+        Ok(Value::String(v))
+    }
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Value, E> {
+        Ok(Value::List(
+            v.iter().map(|&b| Value::Int(b.into())).collect(),
+        ))
+    }
+    fn visit_unit<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::None)
+    }
+    fn visit_none<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::None)
+    }
+    fn visit_some<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+    fn visit_newtype_struct<D: de::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(v) = seq.next_element()? {
+            values.push(v);
+        }
+        Ok(Value::List(values))
+    }
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut entries: Vec<(Value, Value)> = Vec::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some(entry) = map.next_entry()? {
+            entries.push(entry);
+        }
+        if entries.iter().all(|(k, _)| matches!(k, Value::String(_))) {
+            Ok(Value::Struct(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| match k {
+                        Value::String(k) => (k, v),
+                        _ => unreachable!(),
+                    })
+                    .collect(),
+            ))
+        } else {
+            Ok(Value::Map(entries))
+        }
+    }
+}
+
+/// Why a value couldn't be reflected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReflectError(pub String);
+
+impl std::fmt::Display for ReflectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ReflectError {}
+
+impl ser::Error for ReflectError {
+    fn custom<M: std::fmt::Display>(msg: M) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+impl de::Error for ReflectError {
+    fn custom<M: std::fmt::Display>(msg: M) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+/// A field of a component, as declared in `components!`. Fields serde skips are left out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FieldInfo {
+    /// The field's name as serde serializes it, or its index among the serialized fields for
+    /// tuple structs.
+    pub name: &'static str,
+    /// The field's type as written in the declaration, e.g. `Option<Entity>`.
+    pub ty: &'static str,
+    /// Where the field is in the component, in bytes.
+    pub offset: usize,
+}
+
+/// Implemented by `components!` for every component.
+pub trait Reflect {
+    const FIELDS: &'static [FieldInfo];
+
+    /// The field `name`, or `None` if there is no such field.
+    fn field(&self, name: &str) -> Option<Result<Value, ReflectError>>;
+
+    /// Sets the field `name`, converting `value` to the field's type through its `Deserialize`
+    /// impl. Fails, leaving the field as it was, if there is no such field or `value` doesn't fit.
+    fn set_field(&mut self, name: &str, value: Value) -> Result<(), ReflectError>;
+
+    /// Like [`Reflect::field`], but `path` can go into the field, e.g. `target.id` or `items.0`.
+    fn get_path(&self, path: &str) -> Result<Value, ReflectError> {
+        let (name, rest) = split_path(path);
+        let value = self
+            .field(name)
+            .ok_or_else(|| ReflectError(format!("no field `{name}`")))??;
+        match rest {
+            Some(rest) => value
+                .path(rest.split('.'))
+                .cloned()
+                .ok_or_else(|| ReflectError(format!("no field `{path}`"))),
+            None => Ok(value),
+        }
+    }
+
+    /// Like [`Reflect::set_field`], but `path` can go into the field, e.g. `target.id`.
+    fn set_path(&mut self, path: &str, value: Value) -> Result<(), ReflectError> {
+        let (name, rest) = split_path(path);
+        let Some(rest) = rest else {
+            return self.set_field(name, value);
+        };
+        let mut field = self
+            .field(name)
+            .ok_or_else(|| ReflectError(format!("no field `{name}`")))??;
+        *field
+            .path_mut(rest.split('.'))
+            .ok_or_else(|| ReflectError(format!("no field `{path}`")))? = value;
+        self.set_field(name, field)
+    }
+}
+
+fn split_path(path: &str) -> (&str, Option<&str>) {
+    match path.split_once('.') {
+        Some((name, rest)) => (name, Some(rest)),
+        None => (path, None),
+    }
+}
+
+/// `v` as a [`Value`]. Fails if `v`'s `Serialize` impl fails or it has an integer that doesn't fit
+/// in an `i128`.
+pub fn to_value<T: Serialize + ?Sized>(v: &T) -> Result<Value, ReflectError> {
+    v.serialize(ValueSerializer)
+}
+
+/// `value` as a `T`, through `T`'s `Deserialize` impl. Ints are accepted for floats, and strings
+/// for unit enum variants.
+pub fn from_value<T: de::DeserializeOwned>(value: Value) -> Result<T, ReflectError> {
+    T::deserialize(value)
+}
+
+struct ValueSerializer;
+
+fn int(v: impl TryInto<i128> + std::fmt::Display + Copy) -> Result<Value, ReflectError> {
+    v.try_into()
+        .map(Value::Int)
+        .map_err(|_| ReflectError(format!("{v} doesn't fit in an i128")))
+}
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = ReflectError;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = SeqSerializer;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = StructSerializer;
+    type SerializeStructVariant = StructSerializer;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, ReflectError> {
+        Ok(Value::Bool(v))
+    }
+    fn serialize_i8(self, v: i8) -> Result<Value, ReflectError> {
+        int(v)
+    }
+    fn serialize_i16(self, v: i16) -> Result<Value, ReflectError> {
+        int(v)
+    }
+    fn serialize_i32(self, v: i32) -> Result<Value, ReflectError> {
+        int(v)
+    }
+    fn serialize_i64(self, v: i64) -> Result<Value, ReflectError> {
+        int(v)
+    }
+    fn serialize_i128(self, v: i128) -> Result<Value, ReflectError> {
+        int(v)
+    }
+    fn serialize_u8(self, v: u8) -> Result<Value, ReflectError> {
+        int(v)
+    }
+    fn serialize_u16(self, v: u16) -> Result<Value, ReflectError> {
+        int(v)
+    }
+    fn serialize_u32(self, v: u32) -> Result<Value, ReflectError> {
+        int(v)
+    }
+    fn serialize_u64(self, v: u64) -> Result<Value, ReflectError> {
+        int(v)
+    }
+    fn serialize_u128(self, v: u128) -> Result<Value, ReflectError> {
+        int(v)
+    }
+    fn serialize_f32(self, v: f32) -> Result<Value, ReflectError> {
+        Ok(Value::Float(v as f64))
+    }
+    fn serialize_f64(self, v: f64) -> Result<Value, ReflectError> {
+        Ok(Value::Float(v))
+    }
+    fn serialize_char(self, v: char) -> Result<Value, ReflectError> {
+        Ok(Value::String(v.to_string()))
+    }
+    fn serialize_str(self, v: &str) -> Result<Value, ReflectError> {
+        Ok(Value::String(v.to_string()))
+    }
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, ReflectError> {
+        Ok(Value::List(
+            v.iter().map(|&b| Value::Int(b.into())).collect(),
+        ))
+    }
+    fn serialize_none(self) -> Result<Value, ReflectError> {
+        Ok(Value::None)
+    }
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value, ReflectError> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<Value, ReflectError> {
+        Ok(Value::None)
+    }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, ReflectError> {
+        Ok(Value::None)
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Value, ReflectError> {
+        Ok(Value::Variant(variant.to_string(), Box::new(Value::None)))
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value, ReflectError> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value, ReflectError> {
+        Ok(Value::Variant(
+            variant.to_string(),
+            Box::new(value.serialize(self)?),
+        ))
+    }
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer, ReflectError> {
+        Ok(SeqSerializer {
+            values: Vec::with_capacity(len.unwrap_or(0)),
+            entity: false,
+            variant: None,
+        })
+    }
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer, ReflectError> {
+        self.serialize_seq(Some(len))
+    }
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer, ReflectError> {
+        let mut seq = self.serialize_seq(Some(len))?;
+        seq.entity = name == "Entity";
+        Ok(seq)
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer, ReflectError> {
+        let mut seq = self.serialize_seq(Some(len))?;
+        seq.variant = Some(variant);
+        Ok(seq)
+    }
+    fn serialize_map(self, len: Option<usize>) -> Result<MapSerializer, ReflectError> {
+        Ok(MapSerializer {
+            entries: Vec::with_capacity(len.unwrap_or(0)),
+            key: None,
+        })
+    }
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<StructSerializer, ReflectError> {
+        Ok(StructSerializer {
+            fields: Vec::with_capacity(len),
+            variant: None,
+        })
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<StructSerializer, ReflectError> {
+        Ok(StructSerializer {
+            fields: Vec::with_capacity(len),
+            variant: Some(variant),
+        })
+    }
+}
+
+fn in_variant(variant: Option<&'static str>, value: Value) -> Value {
+    match variant {
+        Some(variant) => Value::Variant(variant.to_string(), Box::new(value)),
+        None => value,
+    }
+}
+
+struct SeqSerializer {
+    values: Vec<Value>,
+    /// Serializing an `Entity`, which is a tuple struct named "Entity" of its id and version.
+    entity: bool,
+    variant: Option<&'static str>,
+}
+
+impl SeqSerializer {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ReflectError> {
+        self.values.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value, ReflectError> {
+        if self.entity {
+            if let [Value::Int(id), Value::Int(version)] = self.values[..] {
+                let entity = u32::try_from(version)
+                    .ok()
+                    .and_then(std::num::NonZeroU32::new)
+                    .zip(u32::try_from(id).ok())
+                    .map(|(version, id)| Entity::new(id, version));
+                if let Some(entity) = entity {
+                    return Ok(Value::Entity(entity));
+                }
+            }
+        }
+        Ok(in_variant(self.variant, Value::List(self.values)))
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Value;
+    type Error = ReflectError;
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ReflectError> {
+        self.push(value)
+    }
+    fn end(self) -> Result<Value, ReflectError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Value;
+    type Error = ReflectError;
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ReflectError> {
+        self.push(value)
+    }
+    fn end(self) -> Result<Value, ReflectError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Value;
+    type Error = ReflectError;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ReflectError> {
+        self.push(value)
+    }
+    fn end(self) -> Result<Value, ReflectError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SeqSerializer {
+    type Ok = Value;
+    type Error = ReflectError;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ReflectError> {
+        self.push(value)
+    }
+    fn end(self) -> Result<Value, ReflectError> {
+        self.finish()
+    }
+}
+
+struct MapSerializer {
+    entries: Vec<(Value, Value)>,
+    key: Option<Value>,
+}
+
+impl ser::SerializeMap for MapSerializer {
+    type Ok = Value;
+    type Error = ReflectError;
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), ReflectError> {
+        self.key = Some(key.serialize(ValueSerializer)?);
+        Ok(())
+    }
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ReflectError> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| ReflectError("map value without a key".to_string()))?;
+        self.entries.push((key, value.serialize(ValueSerializer)?));
+        Ok(())
+    }
+    fn end(self) -> Result<Value, ReflectError> {
+        Ok(Value::Map(self.entries))
+    }
+}
+
+struct StructSerializer {
+    fields: Vec<(String, Value)>,
+    variant: Option<&'static str>,
+}
+
+impl ser::SerializeStruct for StructSerializer {
+    type Ok = Value;
+    type Error = ReflectError;
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), ReflectError> {
+        self.fields
+            .push((key.to_string(), value.serialize(ValueSerializer)?));
+        Ok(())
+    }
+    fn end(self) -> Result<Value, ReflectError> {
+        Ok(in_variant(self.variant, Value::Struct(self.fields)))
+    }
+}
+
+impl ser::SerializeStructVariant for StructSerializer {
+    type Ok = Value;
+    type Error = ReflectError;
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), ReflectError> {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+    fn end(self) -> Result<Value, ReflectError> {
+        ser::SerializeStruct::end(self)
+    }
+}
+
+impl<'de> IntoDeserializer<'de, ReflectError> for Value {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Value {
+    type Error = ReflectError;
+
+    fn deserialize_any<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, ReflectError> {
+        match self {
+            Value::None => visitor.visit_unit(),
+            Value::Bool(v) => visitor.visit_bool(v),
+            Value::Int(v) => match (i64::try_from(v), u64::try_from(v)) {
+                (Ok(v), _) => visitor.visit_i64(v),
+                (_, Ok(v)) => visitor.visit_u64(v),
+                _ => visitor.visit_i128(v),
+            },
+            Value::Float(v) => visitor.visit_f64(v),
+            Value::String(v) => visitor.visit_string(v),
+            Value::Entity(e) => visitor.visit_seq(de::value::SeqDeserializer::new(
+                [Value::Int(e.id.into()), Value::Int(e.version.get().into())].into_iter(),
+            )),
+            Value::List(values) => {
+                visitor.visit_seq(de::value::SeqDeserializer::new(values.into_iter()))
+            }
+            Value::Struct(fields) => {
+                visitor.visit_map(de::value::MapDeserializer::new(fields.into_iter()))
+            }
+            Value::Map(entries) => {
+                visitor.visit_map(de::value::MapDeserializer::new(entries.into_iter()))
+            }
+            Value::Variant(variant, content) => visitor.visit_map(de::value::MapDeserializer::new(
+                std::iter::once((variant, *content)),
+            )),
+        }
+    }
+
+    fn deserialize_option<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, ReflectError> {
+        match self {
+            Value::None => visitor.visit_none(),
+            value => visitor.visit_some(value),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ReflectError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ReflectError> {
+        match self {
+            Value::Variant(variant, content) => visitor.visit_enum(EnumDeserializer {
+                variant,
+                content: *content,
+            }),
+            Value::String(variant) => visitor.visit_enum(EnumDeserializer {
+                variant,
+                content: Value::None,
+            }),
+            // how variants look after a trip through JSON
+            Value::Struct(mut fields) if fields.len() == 1 => {
+                let (variant, content) = fields.pop().unwrap();
+                visitor.visit_enum(EnumDeserializer { variant, content })
+            }
+            value => Err(ReflectError(format!(
+                "expected an enum, got a {}",
+                value.kind()
+            ))),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf unit
+        unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct EnumDeserializer {
+    variant: String,
+    content: Value,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumDeserializer {
+    type Error = ReflectError;
+    type Variant = Value;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Value), ReflectError> {
+        let variant = seed.deserialize(self.variant.into_deserializer())?;
+        Ok((variant, self.content))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Value {
+    type Error = ReflectError;
+
+    fn unit_variant(self) -> Result<(), ReflectError> {
+        Deserialize::deserialize(self)
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, ReflectError> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: de::Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, ReflectError> {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+
+    fn struct_variant<V: de::Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ReflectError> {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+}
@@ -0,0 +1,401 @@
+//! Mirroring part of an authoritative world on clients.
+//!
+//! Components marked `#[replicate]` in `components!` are replicated; everything else stays on the
+//! server, and entities without any replicated component aren't sent at all. The server keeps, per
+//! client, a hash of every component that client has been sent and turns what changed since into
+//! a packet. Packets are expected to arrive reliably and in
+//! order, like over TCP or a reliable channel.
+//!
+//! ```ignore
+//! // server
+//! let client = server.connect();
+//! for (client, packet) in server.update(&ecs) {
+//!     send(client, packet);
+//! }
+//!
+//! // client
+//! let mut replica = eliecs::replication::Client::new();
+//! replica.receive(&mut local_ecs, &packet)?;
+//! ```
+//!
+//! Which entities a client sees is decided by an [`Interest`]: entities entering a client's
+//! interest are spawned on it, and despawned when they leave. With
+//! [`Server::set_budget`] packets are capped in size, sending the highest priority entities first
+//! and holding the rest back for later packets.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Debug,
+    hash::Hash,
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{Entity, EntityMap, WorldDelta};
+
+/// Implemented by the `Ecs` generated by `components!`.
+pub trait Replicate {
+    type Component: Clone + Debug + Serialize + DeserializeOwned;
+    type ComponentType: Copy + Debug + Eq + Hash + Serialize + DeserializeOwned;
+
+    /// What a client that has been sent `known` needs to receive to catch up with `self`, looking
+    /// only at replicated components of `relevant` entities that have one.
+    fn replication_delta(
+        &self,
+        known: &Known<Self::ComponentType>,
+        relevant: &mut dyn FnMut(Entity) -> bool,
+    ) -> Delta<Self>;
+
+    /// The type of `c` and the hash [`Known`] keeps of it.
+    fn component_hash(c: &Self::Component) -> (Self::ComponentType, u64);
+
+    /// Applies a delta received from the server, spawning local entities for the server's and
+    /// recording them in `map`. Entity references in components are rewritten through `map`.
+    /// Components referencing entities missing from it are also kept in `unresolved` as received,
+    /// and mapped again whenever a later delta spawns something.
+    fn apply_mapped_delta(
+        &mut self,
+        delta: Delta<Self>,
+        map: &mut EntityMap,
+        unresolved: &mut Unresolved<Self>,
+    );
+}
+
+pub type Delta<W> = WorldDelta<<W as Replicate>::Component, <W as Replicate>::ComponentType>;
+
+/// Components a client received that reference entities it didn't have, by the server's entity
+/// and type.
+pub type Unresolved<W> =
+    HashMap<(Entity, <W as Replicate>::ComponentType), <W as Replicate>::Component>;
+
+pub type ClientId = u32;
+
+/// Decides which entities each client gets and in which order.
+///
+/// Closures `FnMut(ClientId, &W, Entity) -> bool` implement this with every entity at the same
+/// priority, e.g. `|client, world: &Ecs, e| world.team(e.id).map(|t| t.0) == Some(client)`.
+pub trait Interest<W> {
+    fn relevant(&mut self, client: ClientId, world: &W, e: Entity) -> bool;
+
+    /// Higher goes first when the packet budget can't fit everything. Entities that get held
+    /// back keep their priority and add it again next time, so they can't be starved.
+    fn priority(&mut self, client: ClientId, world: &W, e: Entity) -> f32 {
+        let _ = (client, world, e);
+        1.0
+    }
+}
+
+impl<W, F> Interest<W> for F
+where
+    F: FnMut(ClientId, &W, Entity) -> bool,
+{
+    fn relevant(&mut self, client: ClientId, world: &W, e: Entity) -> bool {
+        self(client, world, e)
+    }
+}
+
+/// Every client sees every entity.
+pub struct Everything;
+
+impl<W> Interest<W> for Everything {
+    fn relevant(&mut self, _client: ClientId, _world: &W, _e: Entity) -> bool {
+        true
+    }
+}
+
+/// What a client has been sent: the server's entities it has, with a hash of each of their
+/// replicated components as last sent.
+#[derive(Clone, Debug)]
+pub struct Known<T> {
+    entities: HashMap<Entity, Vec<(T, u64)>>,
+}
+
+impl<T: Copy + Eq> Known<T> {
+    pub fn new() -> Self {
+        Self {
+            entities: HashMap::new(),
+        }
+    }
+
+    pub fn contains(&self, e: Entity) -> bool {
+        self.entities.contains_key(&e)
+    }
+
+    /// The hash of the `ty` component of `e` the client has, if it has one.
+    pub fn hash(&self, e: Entity, ty: T) -> Option<u64> {
+        self.entities
+            .get(&e)?
+            .iter()
+            .find(|(t, _)| *t == ty)
+            .map(|(_, hash)| *hash)
+    }
+
+    /// Every entity the client has, with the types of its components.
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, impl Iterator<Item = T> + '_)> + '_ {
+        self.entities
+            .iter()
+            .map(|(e, components)| (*e, components.iter().map(|(ty, _)| *ty)))
+    }
+
+    /// Records that the client has been sent `delta`.
+    fn apply<W: Replicate<ComponentType = T>>(&mut self, delta: &Delta<W>) {
+        for e in &delta.despawned {
+            self.entities.remove(e);
+        }
+        for e in &delta.spawned {
+            self.entities.insert(*e, Vec::new());
+        }
+        for (e, ty) in &delta.removed {
+            if let Some(components) = self.entities.get_mut(e) {
+                components.retain(|(t, _)| t != ty);
+            }
+        }
+        for (e, c) in delta.inserted.iter().chain(&delta.updated) {
+            let Some(components) = self.entities.get_mut(e) else {
+                continue;
+            };
+            let (ty, hash) = W::component_hash(c);
+            match components.iter_mut().find(|(t, _)| *t == ty) {
+                Some((_, known_hash)) => *known_hash = hash,
+                None => components.push((ty, hash)),
+            }
+        }
+    }
+}
+
+impl<T: Copy + Eq> Default for Known<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct ClientState<T> {
+    known: Known<T>,
+    /// Accumulated priority of entities held back by the budget.
+    deferred: HashMap<Entity, f32>,
+}
+
+/// The server side: tracks what each connected client has been sent.
+pub struct Server<W: Replicate> {
+    clients: BTreeMap<ClientId, ClientState<W::ComponentType>>,
+    next_client: ClientId,
+    budget: Option<usize>,
+}
+
+impl<W: Replicate> Server<W> {
+    pub fn new() -> Self {
+        Self {
+            clients: BTreeMap::new(),
+            next_client: 0,
+            budget: None,
+        }
+    }
+
+    /// Caps packets at roughly `bytes`. Despawns are always sent; a packet that would otherwise be
+    /// empty still carries one entity even if that goes over budget.
+    pub fn set_budget(&mut self, bytes: Option<usize>) {
+        self.budget = bytes;
+    }
+
+    /// Adds a client that has nothing yet; its first packet spawns every replicated entity.
+    pub fn connect(&mut self) -> ClientId {
+        let client = self.next_client;
+        self.next_client += 1;
+        self.clients.insert(
+            client,
+            ClientState {
+                known: Known::new(),
+                deferred: HashMap::new(),
+            },
+        );
+        client
+    }
+
+    pub fn disconnect(&mut self, client: ClientId) {
+        self.clients.remove(&client);
+    }
+
+    pub fn clients(&self) -> impl Iterator<Item = ClientId> + '_ {
+        self.clients.keys().copied()
+    }
+
+    /// The packet bringing `client` up to date with `world`, or `None` if nothing it can see
+    /// changed. The client is assumed to receive every packet returned.
+    pub fn update_client(&mut self, client: ClientId, world: &W) -> Option<Vec<u8>> {
+        self.update_client_with(client, world, &mut Everything)
+    }
+
+    pub fn update_client_with(
+        &mut self,
+        client: ClientId,
+        world: &W,
+        interest: &mut dyn Interest<W>,
+    ) -> Option<Vec<u8>> {
+        let state = self.clients.get_mut(&client)?;
+        let mut delta =
+            world.replication_delta(&state.known, &mut |e| interest.relevant(client, world, e));
+        if let Some(budget) = self.budget {
+            delta = fit_budget::<W>(delta, budget, &mut state.deferred, |e| {
+                interest.priority(client, world, e)
+            });
+        }
+        if delta.is_empty() {
+            return None;
+        }
+        state.known.apply::<W>(&delta);
+        Some(encode(&delta))
+    }
+
+    /// [`Server::update_client`] for every connected client.
+    pub fn update(&mut self, world: &W) -> Vec<(ClientId, Vec<u8>)> {
+        self.update_with(world, &mut Everything)
+    }
+
+    /// [`Server::update_client_with`] for every connected client.
+    pub fn update_with(
+        &mut self,
+        world: &W,
+        interest: &mut dyn Interest<W>,
+    ) -> Vec<(ClientId, Vec<u8>)> {
+        let clients = self.clients().collect::<Vec<_>>();
+        clients
+            .into_iter()
+            .filter_map(|client| Some((client, self.update_client_with(client, world, interest)?)))
+            .collect()
+    }
+}
+
+impl<W: Replicate> Default for Server<W> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The client side: applies packets from a [`Server`] to a local world.
+///
+/// References to entities the client hasn't been sent, because they aren't relevant to it or
+/// haven't fit in a packet yet, point at [`Entity::DANGLING`] until those entities arrive.
+#[derive(Clone, Debug)]
+pub struct Client<W: Replicate> {
+    map: EntityMap,
+    unresolved: Unresolved<W>,
+}
+
+impl<W: Replicate> Client<W> {
+    pub fn new() -> Self {
+        let mut map = EntityMap::new();
+        map.set_fallback(Some(Entity::DANGLING));
+        Self {
+            map,
+            unresolved: HashMap::new(),
+        }
+    }
+
+    pub fn receive(&mut self, world: &mut W, packet: &[u8]) -> Result<(), bincode::Error> {
+        let delta: Delta<W> = bincode::deserialize(packet)?;
+        world.apply_mapped_delta(delta, &mut self.map, &mut self.unresolved);
+        Ok(())
+    }
+
+    /// The local entity mirroring the server's `e`.
+    pub fn local(&self, e: Entity) -> Option<Entity> {
+        self.map.get(e)
+    }
+
+    /// Server entity -> local entity for everything currently replicated.
+    pub fn entity_map(&self) -> &EntityMap {
+        &self.map
+    }
+}
+
+impl<W: Replicate> Default for Client<W> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Everything a delta says about one entity; these are sent or held back as a whole.
+struct EntityChanges<C, T> {
+    spawned: bool,
+    inserted: Vec<C>,
+    updated: Vec<C>,
+    removed: Vec<T>,
+}
+
+impl<C, T> Default for EntityChanges<C, T> {
+    fn default() -> Self {
+        Self {
+            spawned: false,
+            inserted: Vec::new(),
+            updated: Vec::new(),
+            removed: Vec::new(),
+        }
+    }
+}
+
+fn fit_budget<W: Replicate>(
+    delta: Delta<W>,
+    budget: usize,
+    deferred: &mut HashMap<Entity, f32>,
+    mut priority: impl FnMut(Entity) -> f32,
+) -> Delta<W> {
+    let mut out = Delta::<W>::new();
+    let mut used = size(&out);
+    for e in delta.despawned {
+        deferred.remove(&e);
+        used += size(&e);
+        out.despawned.push(e);
+    }
+
+    let mut changes = HashMap::<Entity, EntityChanges<W::Component, W::ComponentType>>::new();
+    for e in delta.spawned {
+        changes.entry(e).or_default().spawned = true;
+    }
+    for (e, c) in delta.inserted {
+        changes.entry(e).or_default().inserted.push(c);
+    }
+    for (e, c) in delta.updated {
+        changes.entry(e).or_default().updated.push(c);
+    }
+    for (e, ty) in delta.removed {
+        changes.entry(e).or_default().removed.push(ty);
+    }
+    // held back entities that left the client's interest since don't get to keep their priority
+    deferred.retain(|e, _| changes.contains_key(e));
+
+    let mut changes = changes
+        .into_iter()
+        .map(|(e, c)| (priority(e) + deferred.get(&e).copied().unwrap_or(0.0), e, c))
+        .collect::<Vec<_>>();
+    changes.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.id.cmp(&b.1.id)));
+
+    let mut sent_any = false;
+    for (priority, e, c) in changes {
+        let cost = usize::from(c.spawned) * size(&e)
+            + c.inserted.iter().map(|c| size(&(e, c))).sum::<usize>()
+            + c.updated.iter().map(|c| size(&(e, c))).sum::<usize>()
+            + c.removed.iter().map(|ty| size(&(e, ty))).sum::<usize>();
+        if used + cost > budget && sent_any {
+            deferred.insert(e, priority);
+            continue;
+        }
+        used += cost;
+        sent_any = true;
+        deferred.remove(&e);
+        if c.spawned {
+            out.spawned.push(e);
+        }
+        out.inserted.extend(c.inserted.into_iter().map(|c| (e, c)));
+        out.updated.extend(c.updated.into_iter().map(|c| (e, c)));
+        out.removed.extend(c.removed.into_iter().map(|ty| (e, ty)));
+    }
+    out
+}
+
+fn size<T: Serialize>(v: &T) -> usize {
+    bincode::serialized_size(v).expect("serializing a replication packet") as usize
+}
+
+fn encode<T: Serialize>(v: &T) -> Vec<u8> {
+    bincode::serialize(v).expect("serializing a replication packet")
+}
@@ -0,0 +1,518 @@
+//! Reading and writing serialized worlds with only a [`Schema`], for tools that don't link the
+//! game. Component values become [`Value`]s, and are written back with the widths and layout the
+//! schema gives them, so a save can be converted between formats without changing.
+
+use std::num::NonZeroU32;
+
+use serde::{
+    de::{self, DeserializeSeed},
+    ser::{self, SerializeMap, SerializeSeq, SerializeTuple, SerializeTupleStruct},
+    Deserialize, Serialize,
+};
+
+use crate::{
+    reflect::{Value, ValueVisitor},
+    schema::{ComponentSchema, Schema, TypeSchema},
+    Entity, Pool,
+};
+
+/// A serialized `Ecs`, read through a [`Schema`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct SaveFile {
+    pub existence: Pool<NonZeroU32>,
+    pub free_list: Vec<Entity>,
+    /// One pool per component, in the schema's order.
+    pub pools: Vec<Pool<Value>>,
+}
+
+impl SaveFile {
+    pub fn deserialize<'de, D: de::Deserializer<'de>>(
+        schema: &Schema,
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        let shapes = schema
+            .components
+            .iter()
+            .map(ComponentSchema::shape)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(de::Error::custom)?;
+        deserializer.deserialize_tuple(shapes.len() + 2, SaveVisitor(&shapes))
+    }
+
+    /// Something serializing as the `Ecs` this was read from would.
+    pub fn with_schema<'a>(&'a self, schema: &'a Schema) -> impl Serialize + 'a {
+        WithSchema(schema, self)
+    }
+
+    /// Reads a save written with `bincode::serialize`.
+    pub fn from_bincode(schema: &Schema, bytes: &[u8]) -> Result<Self, bincode::Error> {
+        use bincode::Options;
+        let options = bincode::DefaultOptions::new()
+            .with_fixint_encoding()
+            .allow_trailing_bytes();
+        Self::deserialize(
+            schema,
+            &mut bincode::Deserializer::from_slice(bytes, options),
+        )
+    }
+
+    pub fn to_bincode(&self, schema: &Schema) -> Result<Vec<u8>, bincode::Error> {
+        bincode::serialize(&self.with_schema(schema))
+    }
+
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.existence
+            .iter()
+            .map(|(id, &version)| Entity::new(id, version))
+    }
+
+    pub fn is_alive(&self, e: Entity) -> bool {
+        self.existence.get(e.id) == Some(&e.version)
+    }
+
+    /// The components of `e`, in the schema's order.
+    pub fn components_of<'a>(
+        &'a self,
+        schema: &'a Schema,
+        e: Entity,
+    ) -> Vec<(&'a ComponentSchema, &'a Value)> {
+        if !self.is_alive(e) {
+            return Vec::new();
+        }
+        schema
+            .components
+            .iter()
+            .zip(&self.pools)
+            .filter_map(|(c, pool)| Some((c, pool.get(e.id)?)))
+            .collect()
+    }
+
+    /// What is wrong with the save: corrupted pools, components of dead entities and entities
+    /// that are both alive and free. An empty list means the save can be loaded.
+    pub fn problems(&self, schema: &Schema) -> Vec<String> {
+        let mut problems = Vec::new();
+        if !self.existence.is_consistent() {
+            problems.push("the existence pool is corrupted".to_string());
+        }
+        for (c, pool) in schema.components.iter().zip(&self.pools) {
+            if !pool.is_consistent() {
+                problems.push(format!("the {} pool is corrupted", c.name));
+            }
+            for (id, _) in pool.iter() {
+                if !self.existence.contains(id) {
+                    problems.push(format!("{} on {id}, which isn't alive", c.name));
+                }
+            }
+        }
+        for (i, e) in self.free_list.iter().enumerate() {
+            if self.existence.contains(e.id) {
+                problems.push(format!("{e:?} is free, but {} is alive", e.id));
+            }
+            if self.free_list[..i].iter().any(|other| other.id == e.id) {
+                problems.push(format!("{} is in the free list more than once", e.id));
+            }
+        }
+        problems
+    }
+
+    /// Entities referenced by components that aren't alive, as (referencing entity, component,
+    /// referenced entity). These are allowed, but usually mean something wasn't cleaned up.
+    pub fn dangling_references<'a>(&'a self, schema: &'a Schema) -> Vec<(Entity, &'a str, Entity)> {
+        fn visit(value: &Value, found: &mut Vec<Entity>) {
+            match value {
+                Value::Entity(e) => found.push(*e),
+                Value::List(values) => values.iter().for_each(|v| visit(v, found)),
+                Value::Struct(fields) => fields.iter().for_each(|(_, v)| visit(v, found)),
+                Value::Map(entries) => entries.iter().for_each(|(k, v)| {
+                    visit(k, found);
+                    visit(v, found);
+                }),
+                Value::Variant(_, content) => visit(content, found),
+                _ => {}
+            }
+        }
+
+        let mut dangling = Vec::new();
+        for (c, pool) in schema.components.iter().zip(&self.pools) {
+            for (id, value) in pool.iter() {
+                let Some(&version) = self.existence.get(id) else {
+                    continue;
+                };
+                let mut found = Vec::new();
+                visit(value, &mut found);
+                for target in found {
+                    if !self.is_alive(target) {
+                        dangling.push((Entity::new(id, version), c.name.as_str(), target));
+                    }
+                }
+            }
+        }
+        dangling
+    }
+}
+
+struct SaveVisitor<'a>(&'a [TypeSchema]);
+
+impl<'de> de::Visitor<'de> for SaveVisitor<'_> {
+    type Value = SaveFile;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("a serialized Ecs")
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<SaveFile, A::Error> {
+        let existence = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let free_list = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        let mut pools = Vec::with_capacity(self.0.len());
+        for (i, shape) in self.0.iter().enumerate() {
+            pools.push(
+                seq.next_element_seed(PoolSeed(shape))?
+                    .ok_or_else(|| de::Error::invalid_length(i + 2, &self))?,
+            );
+        }
+        Ok(SaveFile {
+            existence,
+            free_list,
+            pools,
+        })
+    }
+}
+
+struct PoolSeed<'a>(&'a TypeSchema);
+
+impl<'de> DeserializeSeed<'de> for PoolSeed<'_> {
+    type Value = Pool<Value>;
+
+    fn deserialize<D: de::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Pool<Value>, D::Error> {
+        deserializer.deserialize_tuple(2, self)
+    }
+}
+
+impl<'de> de::Visitor<'de> for PoolSeed<'_> {
+    type Value = Pool<Value>;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("a serialized Pool")
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Pool<Value>, A::Error> {
+        let sparse = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let dense = seq
+            .next_element_seed(ShapeSeed(&TypeSchema::Seq(Box::new(TypeSchema::Tuple(
+                vec![TypeSchema::U32, self.0.clone()],
+            )))))?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        let unexpected = |v: &Value| de::Unexpected::Other(v.kind());
+        let Value::List(dense) = dense else {
+            return Err(de::Error::invalid_type(
+                unexpected(&dense),
+                &"a list of entries",
+            ));
+        };
+        let dense = dense
+            .into_iter()
+            .map(|entry| {
+                let Value::List(entry) = entry else {
+                    return Err(de::Error::invalid_type(unexpected(&entry), &"an entry"));
+                };
+                let [index, value] = <[Value; 2]>::try_from(entry)
+                    .map_err(|entry| de::Error::invalid_length(entry.len(), &"an entry"))?;
+                let index = match index {
+                    Value::Int(index) => u32::try_from(index).map_err(|_| {
+                        let index = index.to_string();
+                        de::Error::invalid_value(de::Unexpected::Other(&index), &"an index")
+                    })?,
+                    index => return Err(de::Error::invalid_type(unexpected(&index), &"an index")),
+                };
+                Ok((index, value))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Pool::from_parts(sparse, dense))
+    }
+}
+
+/// Reads a value of the given shape, as [`ShapedValue`] writes it.
+pub struct ShapeSeed<'a>(pub &'a TypeSchema);
+
+impl<'de> DeserializeSeed<'de> for ShapeSeed<'_> {
+    type Value = Value;
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        let visitor = ShapeVisitor(self.0);
+        match self.0 {
+            TypeSchema::Bool => deserializer.deserialize_bool(ValueVisitor),
+            TypeSchema::I8 => deserializer.deserialize_i8(ValueVisitor),
+            TypeSchema::I16 => deserializer.deserialize_i16(ValueVisitor),
+            TypeSchema::I32 => deserializer.deserialize_i32(ValueVisitor),
+            TypeSchema::I64 => deserializer.deserialize_i64(ValueVisitor),
+            TypeSchema::I128 => deserializer.deserialize_i128(ValueVisitor),
+            TypeSchema::U8 => deserializer.deserialize_u8(ValueVisitor),
+            TypeSchema::U16 => deserializer.deserialize_u16(ValueVisitor),
+            TypeSchema::U32 => deserializer.deserialize_u32(ValueVisitor),
+            TypeSchema::U64 => deserializer.deserialize_u64(ValueVisitor),
+            TypeSchema::U128 => deserializer.deserialize_u128(ValueVisitor),
+            TypeSchema::F32 => deserializer.deserialize_f32(ValueVisitor),
+            TypeSchema::F64 => deserializer.deserialize_f64(ValueVisitor),
+            TypeSchema::Char => deserializer.deserialize_char(ValueVisitor),
+            TypeSchema::String => deserializer.deserialize_string(ValueVisitor),
+            TypeSchema::Unit => deserializer.deserialize_unit(ValueVisitor),
+            TypeSchema::Entity => Entity::deserialize(deserializer).map(Value::Entity),
+            TypeSchema::Option(_) => deserializer.deserialize_option(visitor),
+            TypeSchema::Seq(_) => deserializer.deserialize_seq(visitor),
+            TypeSchema::Array(_, len) => deserializer.deserialize_tuple(*len, visitor),
+            TypeSchema::Tuple(items) => deserializer.deserialize_tuple(items.len(), visitor),
+            TypeSchema::Map(..) => deserializer.deserialize_map(visitor),
+            TypeSchema::Struct(_) if deserializer.is_human_readable() => {
+                deserializer.deserialize_map(visitor)
+            }
+            TypeSchema::Struct(fields) => deserializer.deserialize_tuple(fields.len(), visitor),
+            TypeSchema::Named(_) if deserializer.is_human_readable() => {
+                deserializer.deserialize_any(ValueVisitor)
+            }
+            TypeSchema::Named(name) => Err(de::Error::custom(format!(
+                "can't read a {name} from a binary format without knowing its type"
+            ))),
+        }
+    }
+}
+
+struct ShapeVisitor<'a>(&'a TypeSchema);
+
+impl<'de> de::Visitor<'de> for ShapeVisitor<'_> {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "a {}", self.0)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::None)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::None)
+    }
+
+    fn visit_some<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        match self.0 {
+            TypeSchema::Option(t) => ShapeSeed(t).deserialize(deserializer),
+            _ => Err(de::Error::invalid_type(de::Unexpected::Option, &self)),
+        }
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut next = |shape: &TypeSchema, i: usize| {
+            seq.next_element_seed(ShapeSeed(shape))?
+                .ok_or_else(|| de::Error::invalid_length(i, &self))
+        };
+        match self.0 {
+            TypeSchema::Seq(t) => {
+                let mut values = Vec::new();
+                while let Some(v) = seq.next_element_seed(ShapeSeed(t))? {
+                    values.push(v);
+                }
+                Ok(Value::List(values))
+            }
+            TypeSchema::Array(t, len) => {
+                let values = (0..*len).map(|i| next(t, i)).collect::<Result<_, _>>()?;
+                Ok(Value::List(values))
+            }
+            TypeSchema::Tuple(items) => {
+                let values = items
+                    .iter()
+                    .enumerate()
+                    .map(|(i, t)| next(t, i))
+                    .collect::<Result<_, _>>()?;
+                Ok(Value::List(values))
+            }
+            TypeSchema::Struct(fields) => {
+                let values = fields
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (name, t))| Ok((name.clone(), next(t, i)?)))
+                    .collect::<Result<_, _>>()?;
+                Ok(Value::Struct(values))
+            }
+            _ => Err(de::Error::invalid_type(de::Unexpected::Seq, &self)),
+        }
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        match self.0 {
+            TypeSchema::Map(k, v) => {
+                let mut entries = Vec::new();
+                while let Some(key) = map.next_key_seed(ShapeSeed(k))? {
+                    entries.push((key, map.next_value_seed(ShapeSeed(v))?));
+                }
+                Ok(Value::Map(entries))
+            }
+            TypeSchema::Struct(fields) => {
+                let mut values = vec![None; fields.len()];
+                while let Some(key) = map.next_key::<String>()? {
+                    let Some(i) = fields.iter().position(|(name, _)| *name == key) else {
+                        return Err(de::Error::custom(format!("unknown field `{key}`")));
+                    };
+                    values[i] = Some(map.next_value_seed(ShapeSeed(&fields[i].1))?);
+                }
+                let values = fields
+                    .iter()
+                    .zip(values)
+                    .map(|((name, _), v)| {
+                        v.map(|v| (name.clone(), v))
+                            .ok_or_else(|| de::Error::custom(format!("missing field `{name}`")))
+                    })
+                    .collect::<Result<_, _>>()?;
+                Ok(Value::Struct(values))
+            }
+            _ => Err(de::Error::invalid_type(de::Unexpected::Map, &self)),
+        }
+    }
+}
+
+/// Writes `value` as a value of the given shape would be: integers get the shape's width and
+/// structs are tuples in binary formats.
+pub struct ShapedValue<'a>(pub &'a TypeSchema, pub &'a Value);
+
+impl Serialize for ShapedValue<'_> {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        fn int<T: TryFrom<i128>, E: ser::Error>(v: i128, shape: &TypeSchema) -> Result<T, E> {
+            T::try_from(v).map_err(|_| E::custom(format!("{v} doesn't fit in a {shape}")))
+        }
+
+        let ShapedValue(shape, value) = *self;
+        let mismatch = || ser::Error::custom(format!("expected a {shape}, got a {}", value.kind()));
+        match (shape, value) {
+            (TypeSchema::Bool, Value::Bool(v)) => serializer.serialize_bool(*v),
+            (TypeSchema::I8, Value::Int(v)) => serializer.serialize_i8(int(*v, shape)?),
+            (TypeSchema::I16, Value::Int(v)) => serializer.serialize_i16(int(*v, shape)?),
+            (TypeSchema::I32, Value::Int(v)) => serializer.serialize_i32(int(*v, shape)?),
+            (TypeSchema::I64, Value::Int(v)) => serializer.serialize_i64(int(*v, shape)?),
+            (TypeSchema::I128, Value::Int(v)) => serializer.serialize_i128(*v),
+            (TypeSchema::U8, Value::Int(v)) => serializer.serialize_u8(int(*v, shape)?),
+            (TypeSchema::U16, Value::Int(v)) => serializer.serialize_u16(int(*v, shape)?),
+            (TypeSchema::U32, Value::Int(v)) => serializer.serialize_u32(int(*v, shape)?),
+            (TypeSchema::U64, Value::Int(v)) => serializer.serialize_u64(int(*v, shape)?),
+            (TypeSchema::U128, Value::Int(v)) => serializer.serialize_u128(int(*v, shape)?),
+            (TypeSchema::F32, Value::Float(v)) => serializer.serialize_f32(*v as f32),
+            (TypeSchema::F32, Value::Int(v)) => serializer.serialize_f32(*v as f32),
+            (TypeSchema::F64, Value::Float(v)) => serializer.serialize_f64(*v),
+            (TypeSchema::F64, Value::Int(v)) => serializer.serialize_f64(*v as f64),
+            (TypeSchema::Char, Value::String(v)) => {
+                let mut chars = v.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => serializer.serialize_char(c),
+                    _ => Err(mismatch()),
+                }
+            }
+            (TypeSchema::String, Value::String(v)) => serializer.serialize_str(v),
+            (TypeSchema::Unit, Value::None) => serializer.serialize_unit(),
+            (TypeSchema::Entity, Value::Entity(e)) => e.serialize(serializer),
+            (TypeSchema::Option(_), Value::None) => serializer.serialize_none(),
+            (TypeSchema::Option(t), v) => serializer.serialize_some(&ShapedValue(t, v)),
+            (TypeSchema::Seq(t), Value::List(values)) => {
+                let mut seq = serializer.serialize_seq(Some(values.len()))?;
+                for v in values {
+                    seq.serialize_element(&ShapedValue(t, v))?;
+                }
+                seq.end()
+            }
+            (TypeSchema::Array(t, len), Value::List(values)) if values.len() == *len => {
+                let mut tuple = serializer.serialize_tuple(*len)?;
+                for v in values {
+                    tuple.serialize_element(&ShapedValue(t, v))?;
+                }
+                tuple.end()
+            }
+            (TypeSchema::Tuple(items), Value::List(values)) if values.len() == items.len() => {
+                let mut tuple = serializer.serialize_tuple(items.len())?;
+                for (t, v) in items.iter().zip(values) {
+                    tuple.serialize_element(&ShapedValue(t, v))?;
+                }
+                tuple.end()
+            }
+            (TypeSchema::Map(k, v), Value::Map(entries)) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    map.serialize_entry(&ShapedValue(k, key), &ShapedValue(v, value))?;
+                }
+                map.end()
+            }
+            (TypeSchema::Struct(fields), Value::Struct(values)) => {
+                let field = |name: &str| {
+                    values
+                        .iter()
+                        .find(|(n, _)| n == name)
+                        .map(|(_, v)| v)
+                        .ok_or_else(|| ser::Error::custom(format!("missing field `{name}`")))
+                };
+                if serializer.is_human_readable() {
+                    let mut map = serializer.serialize_map(Some(fields.len()))?;
+                    for (name, t) in fields {
+                        map.serialize_entry(name, &ShapedValue(t, field(name)?))?;
+                    }
+                    map.end()
+                } else {
+                    let mut tuple = serializer.serialize_tuple(fields.len())?;
+                    for (name, t) in fields {
+                        tuple.serialize_element(&ShapedValue(t, field(name)?))?;
+                    }
+                    tuple.end()
+                }
+            }
+            (TypeSchema::Named(_), v) if serializer.is_human_readable() => v.serialize(serializer),
+            (TypeSchema::Named(name), _) => Err(ser::Error::custom(format!(
+                "can't write a {name} to a binary format without knowing its type"
+            ))),
+            _ => Err(mismatch()),
+        }
+    }
+}
+
+struct WithSchema<'a>(&'a Schema, &'a SaveFile);
+
+impl Serialize for WithSchema<'_> {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let WithSchema(schema, save) = *self;
+        let mut s = serializer.serialize_tuple(schema.components.len() + 2)?;
+        s.serialize_element(&save.existence)?;
+        s.serialize_element(&save.free_list)?;
+        for (c, pool) in schema.components.iter().zip(&save.pools) {
+            let shape = c.shape().map_err(ser::Error::custom)?;
+            s.serialize_element(&ShapedPool(&shape, pool))?;
+        }
+        s.end()
+    }
+}
+
+struct ShapedPool<'a>(&'a TypeSchema, &'a Pool<Value>);
+
+impl Serialize for ShapedPool<'_> {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        struct Dense<'a>(&'a TypeSchema, &'a [(u32, Value)]);
+
+        impl Serialize for Dense<'_> {
+            fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let mut seq = serializer.serialize_seq(Some(self.1.len()))?;
+                for (index, value) in self.1 {
+                    seq.serialize_element(&(index, ShapedValue(self.0, value)))?;
+                }
+                seq.end()
+            }
+        }
+
+        let (sparse, dense) = self.1.parts();
+        let mut s = serializer.serialize_tuple_struct("Pool", 2)?;
+        s.serialize_field(sparse)?;
+        s.serialize_field(&Dense(self.0, dense))?;
+        s.end()
+    }
+}
@@ -0,0 +1,42 @@
+use crate::Entity;
+
+/// An entity-centric view of a world: one record per live entity, holding its components as the
+/// tagged `ComponentTypeContaining` values generated by `components!`.
+///
+/// Unlike serializing the `Ecs` directly this has no sparse/dense arrays, so it is meant for files
+/// that people edit by hand. `components!` generates a `Scene` alias along with
+/// `Ecs::to_scene` and `Ecs::load_scene`.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Scene<C> {
+    pub entities: Vec<SceneEntity<C>>,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct SceneEntity<C> {
+    /// The entity this record had when it was saved. Loading allocates fresh entities, see the
+    /// `EntityMap` returned by `Ecs::load_scene`.
+    pub entity: Entity,
+    pub components: Vec<C>,
+}
+
+impl<C> Scene<C> {
+    pub fn new() -> Self {
+        Self {
+            entities: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+}
+
+impl<C> Default for Scene<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,434 @@
+//! A description of a component set that tools can read without linking the game, generated by
+//! `components!` as `Ecs::schema()` and serializable to JSON.
+//!
+//! A serialized `Ecs` is a tuple of the existence pool (`Pool<NonZeroU32>`), the free list
+//! (`Vec<Entity>`) and one `Pool` per component, in [`Schema::components`] order. A `Pool<T>` is a
+//! tuple of its sparse array (`Vec<u32>`) and its dense array (`Vec<(u32, T)>`), and an `Entity` a
+//! tuple of its id and version. A `ComponentTypeContaining` is `{"type": name, "value": ..}` in
+//! human readable formats and the variant at the component's `index` in binary ones.
+//!
+//! The ecs has no resources, so only components are described.
+
+use std::{fmt::Display, hash::Hasher};
+
+use serde::{Deserialize, Serialize};
+
+use crate::reflect::FieldInfo;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Schema {
+    /// Bumped when the layout described above changes.
+    pub format: u32,
+    /// Sorted by name, which is also the order of `ComponentType::ALL`.
+    pub components: Vec<ComponentSchema>,
+}
+
+impl Schema {
+    pub const FORMAT: u32 = 1;
+
+    pub fn new(components: Vec<ComponentSchema>) -> Self {
+        Self {
+            format: Self::FORMAT,
+            components,
+        }
+    }
+
+    /// The component called `name`, as declared (`CPosition`) or snake cased (`position`).
+    pub fn component(&self, name: &str) -> Option<&ComponentSchema> {
+        self.components
+            .iter()
+            .find(|c| c.name == name || c.snake_name == name)
+    }
+
+    /// Checks that every component's fields fit its `repr`, see [`ComponentSchema::shape`].
+    pub fn validate(&self) -> Result<(), String> {
+        self.components.iter().try_for_each(|c| c.shape().map(drop))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ComponentSchema {
+    /// The name of the struct, e.g. `CPosition`.
+    pub name: String,
+    /// The name of its pool and accessors, e.g. `position`.
+    pub snake_name: String,
+    /// A hash of `name`, which stays the same when other components are added or removed.
+    pub id: u64,
+    /// The component's variant index in `ComponentTypeContaining`.
+    pub index: u32,
+    pub repr: Repr,
+    pub fields: Vec<FieldSchema>,
+    /// Marked `#[replicate]`.
+    pub replicated: bool,
+    /// Not marked `#[no_clone]`.
+    pub cloned: bool,
+}
+
+impl ComponentSchema {
+    pub fn new(
+        name: &str,
+        snake_name: &str,
+        index: u32,
+        repr: Repr,
+        fields: &[FieldInfo],
+        replicated: bool,
+        cloned: bool,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            snake_name: snake_name.to_string(),
+            id: stable_id(name),
+            index,
+            repr,
+            fields: fields
+                .iter()
+                .map(|field| FieldSchema {
+                    name: field.name.to_string(),
+                    ty: field.ty.to_string(),
+                    shape: TypeSchema::parse(field.ty),
+                })
+                .collect(),
+            replicated,
+            cloned,
+        }
+    }
+}
+
+impl ComponentSchema {
+    /// The shape of the whole component, or why `fields` don't fit `repr`. Schemas from
+    /// `Ecs::schema` always fit; hand-edited ones might not.
+    pub fn shape(&self) -> Result<TypeSchema, String> {
+        Ok(match self.repr {
+            Repr::Struct => TypeSchema::Struct(
+                self.fields
+                    .iter()
+                    .map(|field| (field.name.clone(), field.shape.clone()))
+                    .collect(),
+            ),
+            Repr::Newtype => match &self.fields[..] {
+                [field] => field.shape.clone(),
+                fields => {
+                    return Err(format!(
+                        "{} is a newtype struct with {} fields",
+                        self.name,
+                        fields.len()
+                    ))
+                }
+            },
+            Repr::Tuple => TypeSchema::Tuple(
+                self.fields
+                    .iter()
+                    .map(|field| field.shape.clone())
+                    .collect(),
+            ),
+            Repr::Unit if !self.fields.is_empty() => {
+                return Err(format!("{} is a unit struct with fields", self.name))
+            }
+            Repr::Unit => TypeSchema::Unit,
+        })
+    }
+}
+
+/// The stable id of the component called `name`: the 64 bit FNV-1a hash of its UTF-8 bytes, so
+/// tools can compute it too.
+pub fn stable_id(name: &str) -> u64 {
+    let mut hasher = crate::hash::StableHasher::new();
+    hasher.write(name.as_bytes());
+    hasher.finish()
+}
+
+/// How serde represents a component, following the shape of its declaration. Serde attributes on
+/// the component aren't taken into account.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Repr {
+    /// `struct CPosition { x: f32, .. }`: a map of fields in human readable formats, a tuple of
+    /// them in binary ones.
+    Struct,
+    /// `struct CName(String)`: just the field.
+    Newtype,
+    /// `struct CPair(u32, u32)`: a tuple of the fields.
+    Tuple,
+    /// `struct CDead;`: nothing.
+    Unit,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FieldSchema {
+    /// The field's name, or its index for tuple structs.
+    pub name: String,
+    /// The type as written, e.g. `Option<Entity>`.
+    pub ty: String,
+    pub shape: TypeSchema,
+}
+
+/// What serde sees of a field's type, as far as it can be told from how the type is written.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TypeSchema {
+    Bool,
+    I8,
+    I16,
+    I32,
+    /// `i64` and `isize`.
+    I64,
+    I128,
+    U8,
+    U16,
+    /// `u32` and `NonZeroU32`.
+    U32,
+    /// `u64` and `usize`.
+    U64,
+    U128,
+    F32,
+    F64,
+    Char,
+    /// `String` and `str`.
+    String,
+    Unit,
+    Entity,
+    Option(Box<TypeSchema>),
+    /// `Vec`, `VecDeque`, sets and slices.
+    Seq(Box<TypeSchema>),
+    Array(Box<TypeSchema>, usize),
+    Tuple(Vec<TypeSchema>),
+    /// `HashMap` and `BTreeMap`.
+    Map(Box<TypeSchema>, Box<TypeSchema>),
+    /// A component's named fields. Types of fields are never parsed as this, since their fields
+    /// aren't known.
+    Struct(Vec<(String, TypeSchema)>),
+    /// Any other type, by its name as written. Tools can only read these from self-describing
+    /// formats like JSON.
+    Named(String),
+}
+
+impl TypeSchema {
+    /// The shape of the type written as `ty`, e.g. `Vec<(Entity, u32)>`. Smart pointers like
+    /// `Box` are seen through, since serde does.
+    pub fn parse(ty: &str) -> Self {
+        let tokens = tokenize(ty);
+        let mut pos = 0;
+        match parse_type(&tokens, &mut pos) {
+            Some(shape) if pos == tokens.len() => shape,
+            _ => TypeSchema::Named(ty.to_string()),
+        }
+    }
+
+    /// Whether values of this type can be read without knowing anything else, i.e. it doesn't
+    /// contain [`TypeSchema::Named`].
+    pub fn is_known(&self) -> bool {
+        match self {
+            TypeSchema::Option(t) | TypeSchema::Seq(t) | TypeSchema::Array(t, _) => t.is_known(),
+            TypeSchema::Tuple(ts) => ts.iter().all(TypeSchema::is_known),
+            TypeSchema::Map(k, v) => k.is_known() && v.is_known(),
+            TypeSchema::Struct(fields) => fields.iter().all(|(_, t)| t.is_known()),
+            TypeSchema::Named(_) => false,
+            _ => true,
+        }
+    }
+}
+
+impl Display for TypeSchema {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let list = |f: &mut std::fmt::Formatter<'_>, ts: &[TypeSchema]| {
+            for (i, t) in ts.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                write!(f, "{t}")?;
+            }
+            Ok(())
+        };
+        match self {
+            TypeSchema::Bool => f.write_str("bool"),
+            TypeSchema::I8 => f.write_str("i8"),
+            TypeSchema::I16 => f.write_str("i16"),
+            TypeSchema::I32 => f.write_str("i32"),
+            TypeSchema::I64 => f.write_str("i64"),
+            TypeSchema::I128 => f.write_str("i128"),
+            TypeSchema::U8 => f.write_str("u8"),
+            TypeSchema::U16 => f.write_str("u16"),
+            TypeSchema::U32 => f.write_str("u32"),
+            TypeSchema::U64 => f.write_str("u64"),
+            TypeSchema::U128 => f.write_str("u128"),
+            TypeSchema::F32 => f.write_str("f32"),
+            TypeSchema::F64 => f.write_str("f64"),
+            TypeSchema::Char => f.write_str("char"),
+            TypeSchema::String => f.write_str("String"),
+            TypeSchema::Unit => f.write_str("()"),
+            TypeSchema::Entity => f.write_str("Entity"),
+            TypeSchema::Option(t) => write!(f, "Option<{t}>"),
+            TypeSchema::Seq(t) => write!(f, "Vec<{t}>"),
+            TypeSchema::Array(t, n) => write!(f, "[{t}; {n}]"),
+            TypeSchema::Tuple(ts) if ts.len() == 1 => write!(f, "({},)", ts[0]),
+            TypeSchema::Tuple(ts) => {
+                f.write_str("(")?;
+                list(f, ts)?;
+                f.write_str(")")
+            }
+            TypeSchema::Map(k, v) => write!(f, "Map<{k}, {v}>"),
+            TypeSchema::Struct(fields) => {
+                f.write_str("{ ")?;
+                for (i, (name, t)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{name}: {t}")?;
+                }
+                f.write_str(" }")
+            }
+            TypeSchema::Named(name) => f.write_str(name),
+        }
+    }
+}
+
+fn tokenize(ty: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut chars = ty.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        if c.is_alphanumeric() || c == '_' || c == '\'' {
+            let mut word = c.to_string();
+            while let Some(&c) = chars.peek() {
+                if !(c.is_alphanumeric() || c == '_') {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            tokens.push(word);
+        } else if c == ':' && chars.peek() == Some(&':') {
+            chars.next();
+            tokens.push("::".to_string());
+        } else {
+            tokens.push(c.to_string());
+        }
+    }
+    tokens
+}
+
+fn expect(tokens: &[String], pos: &mut usize, token: &str) -> Option<()> {
+    (tokens.get(*pos)? == token).then(|| *pos += 1)
+}
+
+/// `<A, B, ..>` after a type's name.
+fn parse_generics(tokens: &[String], pos: &mut usize) -> Option<Vec<TypeSchema>> {
+    let mut args = Vec::new();
+    if tokens.get(*pos).map(String::as_str) != Some("<") {
+        return Some(args);
+    }
+    *pos += 1;
+    loop {
+        // lifetimes don't change the shape
+        if tokens.get(*pos)?.starts_with('\'') {
+            *pos += 1;
+        } else {
+            args.push(parse_type(tokens, pos)?);
+        }
+        match tokens.get(*pos)?.as_str() {
+            "," => *pos += 1,
+            ">" => break,
+            _ => return None,
+        }
+        if tokens.get(*pos)? == ">" {
+            break;
+        }
+    }
+    *pos += 1;
+    Some(args)
+}
+
+fn parse_type(tokens: &[String], pos: &mut usize) -> Option<TypeSchema> {
+    let token = tokens.get(*pos)?.clone();
+    *pos += 1;
+    match token.as_str() {
+        "&" => {
+            if tokens.get(*pos)?.starts_with('\'') {
+                *pos += 1;
+            }
+            if tokens.get(*pos)? == "mut" {
+                *pos += 1;
+            }
+            parse_type(tokens, pos)
+        }
+        "(" => {
+            let mut items = Vec::new();
+            let mut trailing_comma = false;
+            while tokens.get(*pos)? != ")" {
+                items.push(parse_type(tokens, pos)?);
+                trailing_comma = tokens.get(*pos)? == ",";
+                if trailing_comma {
+                    *pos += 1;
+                }
+            }
+            *pos += 1;
+            Some(match items.len() {
+                0 => TypeSchema::Unit,
+                1 if !trailing_comma => items.pop().unwrap(),
+                _ => TypeSchema::Tuple(items),
+            })
+        }
+        "[" => {
+            let item = parse_type(tokens, pos)?;
+            if tokens.get(*pos)? == ";" {
+                *pos += 1;
+                let len = tokens.get(*pos)?.parse().ok()?;
+                *pos += 1;
+                expect(tokens, pos, "]")?;
+                Some(TypeSchema::Array(Box::new(item), len))
+            } else {
+                expect(tokens, pos, "]")?;
+                Some(TypeSchema::Seq(Box::new(item)))
+            }
+        }
+        _ => {
+            // only the last segment of a path matters: `std::collections::HashMap`
+            let mut name = token;
+            if name == "::" {
+                name = tokens.get(*pos)?.clone();
+                *pos += 1;
+            }
+            while tokens.get(*pos).map(String::as_str) == Some("::") {
+                name = tokens.get(*pos + 1)?.clone();
+                *pos += 2;
+            }
+            if !name.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+                return None;
+            }
+            let start = *pos;
+            let mut args = parse_generics(tokens, pos)?;
+            let arg = |args: &mut Vec<TypeSchema>| -> Option<Box<TypeSchema>> {
+                (args.len() == 1).then(|| Box::new(args.pop().unwrap()))
+            };
+            Some(match (name.as_str(), args.len()) {
+                ("bool", 0) => TypeSchema::Bool,
+                ("i8", 0) => TypeSchema::I8,
+                ("i16", 0) => TypeSchema::I16,
+                ("i32", 0) => TypeSchema::I32,
+                ("i64" | "isize", 0) => TypeSchema::I64,
+                ("i128", 0) => TypeSchema::I128,
+                ("u8", 0) => TypeSchema::U8,
+                ("u16", 0) => TypeSchema::U16,
+                ("u32" | "NonZeroU32", 0) => TypeSchema::U32,
+                ("u64" | "usize", 0) => TypeSchema::U64,
+                ("u128", 0) => TypeSchema::U128,
+                ("f32", 0) => TypeSchema::F32,
+                ("f64", 0) => TypeSchema::F64,
+                ("char", 0) => TypeSchema::Char,
+                ("String" | "str", 0) => TypeSchema::String,
+                ("Entity", 0) => TypeSchema::Entity,
+                ("Option", 1) => TypeSchema::Option(arg(&mut args)?),
+                ("Vec" | "VecDeque" | "HashSet" | "BTreeSet", 1) => {
+                    TypeSchema::Seq(arg(&mut args)?)
+                }
+                ("Box" | "Rc" | "Arc", 1) => *arg(&mut args)?,
+                ("HashMap" | "BTreeMap", 2) => {
+                    let v = args.pop().unwrap();
+                    let k = args.pop().unwrap();
+                    TypeSchema::Map(Box::new(k), Box::new(v))
+                }
+                _ => TypeSchema::Named(tokens[start - 1..*pos].concat()),
+            })
+        }
+    }
+}
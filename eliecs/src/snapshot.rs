@@ -0,0 +1,76 @@
+/// A fixed number of world snapshots keyed by frame, for rollback netcode.
+///
+/// Frame `n` is stored in slot `n % capacity`, so saving a frame overwrites the snapshot taken
+/// `capacity` frames earlier. Slots are refilled with `Clone::clone_from`, which the generated
+/// `Ecs` implements by copying pool buffers into the existing allocations.
+///
+/// ```ignore
+/// let mut ring = SnapshotRing::new(8);
+/// ring.save(frame, &ecs);
+/// // misprediction detected for `frame`
+/// ring.restore(frame, &mut ecs);
+/// ```
+#[derive(Clone, Debug)]
+pub struct SnapshotRing<T> {
+    slots: Vec<Option<(u64, T)>>,
+}
+
+impl<T: Clone> SnapshotRing<T> {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "snapshot ring needs at least one slot");
+        Self {
+            slots: (0..capacity).map(|_| None).collect(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn save(&mut self, frame: u64, world: &T) {
+        let i = self.slot(frame);
+        match &mut self.slots[i] {
+            Some((f, snapshot)) => {
+                *f = frame;
+                snapshot.clone_from(world);
+            }
+            slot @ None => *slot = Some((frame, world.clone())),
+        }
+    }
+
+    /// The snapshot of `frame`, if it was saved and hasn't been overwritten since.
+    pub fn get(&self, frame: u64) -> Option<&T> {
+        match &self.slots[self.slot(frame)] {
+            Some((f, snapshot)) if *f == frame => Some(snapshot),
+            _ => None,
+        }
+    }
+
+    pub fn contains(&self, frame: u64) -> bool {
+        self.get(frame).is_some()
+    }
+
+    /// Copies the snapshot of `frame` into `world`. Returns `false`, leaving `world` alone, if
+    /// that frame isn't stored.
+    pub fn restore(&self, frame: u64, world: &mut T) -> bool {
+        if let Some(snapshot) = self.get(frame) {
+            world.clone_from(snapshot);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The most recent frame that has a snapshot.
+    pub fn latest_frame(&self) -> Option<u64> {
+        self.slots.iter().flatten().map(|(f, _)| *f).max()
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.iter_mut().for_each(|slot| *slot = None);
+    }
+
+    fn slot(&self, frame: u64) -> usize {
+        (frame % self.slots.len() as u64) as usize
+    }
+}
@@ -0,0 +1,25 @@
+use crate::pool::PoolStats;
+
+/// Memory and occupancy of a world, as returned by the generated `Ecs::stats`. `T` is the
+/// generated `ComponentType`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EcsStats<T> {
+    pub alive: u32,
+    /// Despawned ids waiting to be reused.
+    pub free: usize,
+    /// How many times an id was despawned with the highest version and started over at 1,
+    /// since the world was created or loaded. After that, stale handles to it can alias new
+    /// entities again.
+    pub version_wraparounds: u64,
+    /// The pool tracking which entities are alive.
+    pub existence: PoolStats,
+    /// One pool per component, in `ComponentType::ALL` order.
+    pub components: Vec<(T, PoolStats)>,
+}
+
+impl<T> EcsStats<T> {
+    /// The existence and component pools added up.
+    pub fn total(&self) -> PoolStats {
+        self.existence + self.components.iter().map(|(_, stats)| *stats).sum()
+    }
+}
@@ -0,0 +1,71 @@
+use std::fmt::{self, Display};
+
+/// Text in aligned columns, as made by the generated `Ecs::dump_table`. Print it to see it.
+///
+/// Cells longer than [`Table::max_width`] chars are cut short, so one big component doesn't push
+/// the other columns off screen.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Table {
+    pub header: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+    /// How many rows were left out, mentioned below the last one.
+    pub omitted: usize,
+    pub max_width: usize,
+}
+
+impl Table {
+    pub const DEFAULT_MAX_WIDTH: usize = 40;
+
+    pub fn new(header: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            header: header.into_iter().map(Into::into).collect(),
+            rows: Vec::new(),
+            omitted: 0,
+            max_width: Self::DEFAULT_MAX_WIDTH,
+        }
+    }
+
+    pub fn push(&mut self, row: impl IntoIterator<Item = impl Into<String>>) {
+        self.rows.push(row.into_iter().map(Into::into).collect());
+    }
+
+    fn cell<'a>(&self, cell: &'a str) -> (&'a str, bool) {
+        match cell.char_indices().nth(self.max_width.saturating_sub(1)) {
+            Some((end, _)) if cell.chars().count() > self.max_width => (&cell[..end], true),
+            _ => (cell, false),
+        }
+    }
+}
+
+impl Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines = std::iter::once(&self.header).chain(&self.rows);
+        let mut widths = vec![0; self.header.len()];
+        for line in lines.clone() {
+            for (width, cell) in widths.iter_mut().zip(line) {
+                let (cell, cut) = self.cell(cell);
+                *width = (*width).max(cell.chars().count() + usize::from(cut));
+            }
+        }
+        for line in lines {
+            let mut out = String::new();
+            for (i, (width, cell)) in widths.iter().zip(line).enumerate() {
+                if i > 0 {
+                    out.push_str("  ");
+                }
+                let (cell, cut) = self.cell(cell);
+                out.push_str(cell);
+                if cut {
+                    out.push('…');
+                }
+                let len = cell.chars().count() + usize::from(cut);
+                out.extend(std::iter::repeat_n(' ', width - len));
+            }
+            writeln!(f, "{}", out.trim_end())?;
+        }
+        if self.omitted > 0 {
+            writeln!(f, "... {} more", self.omitted)?;
+        }
+        Ok(())
+    }
+}
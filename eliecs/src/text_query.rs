@@ -0,0 +1,421 @@
+//! The query language of the debug console, e.g.
+//!
+//! ```text
+//! Position & Name & !Dead where Health.hp < 10 & Name != "boss"
+//! ```
+//!
+//! Before `where` are the components entities must have, or with `!` must not have. After it are
+//! comparisons of a component's fields, looked up through [`reflect`](crate::reflect), with a
+//! number, a string, `true`, `false`, `none` or an entity like `12v3`. Comparing an enum with a
+//! string compares the variant's name.
+
+use std::ops::Range;
+
+use crate::{
+    reflect::{ReflectError, Value},
+    DynQuery, Entity,
+};
+
+/// A parsed query. `T` is the generated `ComponentType`; run it with the generated
+/// `Ecs::query_text`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextQuery<T> {
+    /// The components entities must and must not have, including the ones `conditions` look at.
+    pub query: DynQuery<T>,
+    pub conditions: Vec<Condition<T>>,
+}
+
+/// `component.fields op value`, e.g. `Health.hp < 10`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Condition<T> {
+    pub component: T,
+    pub fields: Vec<String>,
+    pub op: CompareOp,
+    pub value: Value,
+    /// Where the condition is in the source, for errors while checking it.
+    pub span: Range<usize>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CompareOp {
+    fn symbol(self) -> &'static str {
+        match self {
+            CompareOp::Eq => "==",
+            CompareOp::Ne => "!=",
+            CompareOp::Lt => "<",
+            CompareOp::Le => "<=",
+            CompareOp::Gt => ">",
+            CompareOp::Ge => ">=",
+        }
+    }
+
+    fn holds(self, ordering: std::cmp::Ordering) -> bool {
+        match self {
+            CompareOp::Eq => ordering.is_eq(),
+            CompareOp::Ne => ordering.is_ne(),
+            CompareOp::Lt => ordering.is_lt(),
+            CompareOp::Le => ordering.is_le(),
+            CompareOp::Gt => ordering.is_gt(),
+            CompareOp::Ge => ordering.is_ge(),
+        }
+    }
+}
+
+/// A query that couldn't be parsed or checked, with the byte range of the source at fault.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QueryError {
+    pub message: String,
+    pub span: Range<usize>,
+}
+
+impl QueryError {
+    fn new(message: impl Into<String>, span: Range<usize>) -> Self {
+        Self {
+            message: message.into(),
+            span,
+        }
+    }
+
+    /// `source` with the error underlined below it, for printing in a console:
+    ///
+    /// ```text
+    /// Position & Nme
+    ///            ^^^ unknown component `Nme`
+    /// ```
+    pub fn render(&self, source: &str) -> String {
+        let start = source[..self.span.start.min(source.len())].chars().count();
+        let width = source
+            .get(self.span.clone())
+            .map_or(0, |s| s.chars().count())
+            .max(1);
+        format!(
+            "{source}\n{}{} {}",
+            " ".repeat(start),
+            "^".repeat(width),
+            self.message
+        )
+    }
+}
+
+impl std::fmt::Display for QueryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} at {}..{}",
+            self.message, self.span.start, self.span.end
+        )
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Literal(Value),
+    And,
+    Not,
+    Dot,
+    Op(CompareOp),
+    Where,
+}
+
+fn tokenize(src: &str) -> Result<Vec<(Token, Range<usize>)>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut chars = src.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        chars.next();
+        let next = chars.peek().map(|&(_, c)| c);
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '&' => {
+                if next == Some('&') {
+                    chars.next();
+                }
+                Token::And
+            }
+            '.' => Token::Dot,
+            '!' | '=' | '<' | '>' => {
+                let op = match (c, next) {
+                    ('!', Some('=')) => Some(CompareOp::Ne),
+                    ('=', Some('=')) => Some(CompareOp::Eq),
+                    ('<', Some('=')) => Some(CompareOp::Le),
+                    ('>', Some('=')) => Some(CompareOp::Ge),
+                    _ => None,
+                };
+                match (c, op) {
+                    (_, Some(op)) => {
+                        chars.next();
+                        Token::Op(op)
+                    }
+                    ('!', None) => Token::Not,
+                    ('=', None) => Token::Op(CompareOp::Eq),
+                    ('<', None) => Token::Op(CompareOp::Lt),
+                    _ => Token::Op(CompareOp::Gt),
+                }
+            }
+            '"' => {
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, c)) => s.push(c),
+                            None => break,
+                        },
+                        Some((_, c)) => s.push(c),
+                        None => {
+                            return Err(QueryError::new("unterminated string", start..src.len()))
+                        }
+                    }
+                }
+                Token::Literal(Value::String(s))
+            }
+            c if c.is_ascii_digit() || c == '-' => {
+                // after a dot it's a tuple field, and `.0.1` is two of them rather than a float
+                let field = matches!(tokens.last(), Some((Token::Dot, _)));
+                let mut end = start + c.len_utf8();
+                while let Some(&(i, c)) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || (c == '.' && !field) || c == '_') {
+                        break;
+                    }
+                    chars.next();
+                    end = i + c.len_utf8();
+                }
+                let text = &src[start..end];
+                Token::Literal(number(text).ok_or_else(|| {
+                    QueryError::new(format!("`{text}` isn't a number or entity"), start..end)
+                })?)
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut end = start + c.len_utf8();
+                while let Some(&(i, c)) = chars.peek() {
+                    if !(c.is_alphanumeric() || c == '_') {
+                        break;
+                    }
+                    chars.next();
+                    end = i + c.len_utf8();
+                }
+                match &src[start..end] {
+                    "where" => Token::Where,
+                    "and" => Token::And,
+                    "not" => Token::Not,
+                    "true" => Token::Literal(Value::Bool(true)),
+                    "false" => Token::Literal(Value::Bool(false)),
+                    "none" => Token::Literal(Value::None),
+                    ident => Token::Ident(ident.to_string()),
+                }
+            }
+            c => {
+                return Err(QueryError::new(
+                    format!("unexpected `{c}`"),
+                    start..start + c.len_utf8(),
+                ))
+            }
+        };
+        let end = chars.peek().map_or(src.len(), |&(i, _)| i);
+        tokens.push((token, start..end));
+    }
+    Ok(tokens)
+}
+
+/// An integer, a float or an entity like `12v3`.
+fn number(text: &str) -> Option<Value> {
+    if let Some((id, version)) = text.split_once('v') {
+        let version = std::num::NonZeroU32::new(version.parse().ok()?)?;
+        return Some(Value::Entity(Entity::new(id.parse().ok()?, version)));
+    }
+    if let Ok(v) = text.parse() {
+        return Some(Value::Int(v));
+    }
+    text.parse().ok().map(Value::Float)
+}
+
+struct Parser<'a, T> {
+    tokens: Vec<(Token, Range<usize>)>,
+    pos: usize,
+    /// Where the source ends, for errors about missing tokens.
+    end: usize,
+    component: &'a dyn Fn(&str) -> Option<T>,
+}
+
+impl<T: Copy + PartialEq> Parser<'_, T> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(t, _)| t)
+    }
+
+    fn span(&self) -> Range<usize> {
+        self.tokens
+            .get(self.pos)
+            .map_or(self.end..self.end, |(_, span)| span.clone())
+    }
+
+    fn next(&mut self, expected: &str) -> Result<(Token, Range<usize>), QueryError> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| QueryError::new(format!("expected {expected}"), self.span()))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn ident(&mut self, expected: &str) -> Result<(String, Range<usize>), QueryError> {
+        match self.next(expected)? {
+            (Token::Ident(ident), span) => Ok((ident, span)),
+            (_, span) => Err(QueryError::new(format!("expected {expected}"), span)),
+        }
+    }
+
+    fn component(&mut self) -> Result<T, QueryError> {
+        let (name, span) = self.ident("a component")?;
+        (self.component)(&name)
+            .ok_or_else(|| QueryError::new(format!("unknown component `{name}`"), span))
+    }
+
+    fn parse(mut self) -> Result<TextQuery<T>, QueryError> {
+        let mut query = DynQuery::new();
+        let mut conditions = Vec::new();
+
+        if !matches!(self.peek(), None | Some(Token::Where)) {
+            loop {
+                if self.peek() == Some(&Token::Not) {
+                    self.pos += 1;
+                    query = query.without(self.component()?);
+                } else {
+                    query = query.with(self.component()?);
+                }
+                if self.peek() != Some(&Token::And) {
+                    break;
+                }
+                self.pos += 1;
+            }
+        }
+
+        if self.peek() == Some(&Token::Where) {
+            self.pos += 1;
+            loop {
+                let condition = self.condition()?;
+                query = query.with(condition.component);
+                conditions.push(condition);
+                if self.peek() != Some(&Token::And) {
+                    break;
+                }
+                self.pos += 1;
+            }
+        }
+
+        if self.pos < self.tokens.len() {
+            return Err(QueryError::new("expected `&` or `where`", self.span()));
+        }
+        Ok(TextQuery { query, conditions })
+    }
+
+    fn condition(&mut self) -> Result<Condition<T>, QueryError> {
+        let start = self.span().start;
+        let component = self.component()?;
+        let mut fields = Vec::new();
+        while self.peek() == Some(&Token::Dot) {
+            self.pos += 1;
+            match self.next("a field")? {
+                (Token::Ident(field), _) => fields.push(field),
+                (Token::Literal(Value::Int(i)), _) if i >= 0 => fields.push(i.to_string()),
+                (_, span) => return Err(QueryError::new("expected a field", span)),
+            }
+        }
+        let op = match self.next("a comparison")? {
+            (Token::Op(op), _) => op,
+            (_, span) => return Err(QueryError::new("expected a comparison", span)),
+        };
+        let (value, span) = match self.next("a value")? {
+            (Token::Literal(value), span) => (value, span),
+            (_, span) => return Err(QueryError::new("expected a value", span)),
+        };
+        Ok(Condition {
+            component,
+            fields,
+            op,
+            value,
+            span: start..span.end,
+        })
+    }
+}
+
+impl<T: Copy + PartialEq> TextQuery<T> {
+    /// Parses `src`, looking up component names with `component`.
+    pub fn parse(src: &str, component: impl Fn(&str) -> Option<T>) -> Result<Self, QueryError> {
+        Parser {
+            tokens: tokenize(src)?,
+            pos: 0,
+            end: src.len(),
+            component: &component,
+        }
+        .parse()
+    }
+
+    /// Whether an entity meets every condition, given its components as [`Value`]s. Fails if a
+    /// component can't be reflected, or a field doesn't exist or can't be compared with the value.
+    pub fn check(
+        &self,
+        mut component: impl FnMut(T) -> Option<Result<Value, ReflectError>>,
+    ) -> Result<bool, QueryError> {
+        for condition in &self.conditions {
+            let value = match component(condition.component) {
+                Some(Ok(value)) => value,
+                Some(Err(e)) => return Err(QueryError::new(e.to_string(), condition.span.clone())),
+                None => return Ok(false),
+            };
+            let field = value
+                .path(condition.fields.iter().map(String::as_str))
+                .ok_or_else(|| {
+                    QueryError::new(
+                        format!("no field `{}`", condition.fields.join(".")),
+                        condition.span.clone(),
+                    )
+                })?;
+            if !compare(field, condition.op, &condition.value).ok_or_else(|| {
+                QueryError::new(
+                    format!(
+                        "can't compare {} {} {}",
+                        field.kind(),
+                        condition.op.symbol(),
+                        condition.value.kind()
+                    ),
+                    condition.span.clone(),
+                )
+            })? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// `None` if the values can't be compared with `op`.
+fn compare(a: &Value, op: CompareOp, b: &Value) -> Option<bool> {
+    let equality = matches!(op, CompareOp::Eq | CompareOp::Ne);
+    let ordering = match (a, b) {
+        (Value::Int(a), Value::Int(b)) => a.cmp(b),
+        (Value::Int(a), Value::Float(b)) => (*a as f64).partial_cmp(b)?,
+        (Value::Float(a), Value::Int(b)) => a.partial_cmp(&(*b as f64))?,
+        (Value::Float(a), Value::Float(b)) => a.partial_cmp(b)?,
+        (Value::String(a), Value::String(b)) => a.cmp(b),
+        (Value::Variant(a, _), Value::String(b)) if equality => a.cmp(b),
+        (Value::Bool(a), Value::Bool(b)) if equality => a.cmp(b),
+        (Value::Entity(a), Value::Entity(b)) if equality => a.to_bits().cmp(&b.to_bits()),
+        (_, Value::None) if equality => {
+            return Some((*a == Value::None) == (op == CompareOp::Eq));
+        }
+        _ => return None,
+    };
+    Some(op.holds(ordering))
+}
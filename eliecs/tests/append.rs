@@ -0,0 +1,55 @@
+mod common;
+
+use common::*;
+
+#[test]
+fn append_moves_entities_and_remaps() {
+    let mut live = Ecs::new();
+    let existing = live.spawn(FatEntity::new().name(CName("existing".into())));
+    let freed = live.spawn(FatEntity::new());
+    live.despawn(freed);
+
+    let mut level = Ecs::new();
+    let gone = level.spawn(FatEntity::new().name(CName("gone".into())));
+    let door = level.spawn(
+        FatEntity::new()
+            .name(CName("door".into()))
+            .position(position(1.0, 0.0, 0.0)),
+    );
+    let lever = level.spawn(FatEntity::new().target(CTarget {
+        target: door,
+        range: 2.0,
+    }));
+    level.despawn(gone);
+
+    let map = live.append(level);
+    assert_eq!(map.len(), 2);
+    assert!(!map.contains(gone));
+
+    let (door, lever) = (map.get(door).unwrap(), map.get(lever).unwrap());
+    // the freed slot is reused before new ids are handed out
+    assert_eq!(door.id, freed.id);
+    assert_ne!(door.version, freed.version);
+
+    assert!(live.is_alive(existing));
+    assert_eq!(live.name_unwrap(existing.id).0, "existing");
+    assert_eq!(live.name_unwrap(door.id).0, "door");
+    assert_eq!(live.position_unwrap(door.id).x, 1.0);
+    assert_eq!(live.target_unwrap(lever.id).target, door);
+    assert_eq!(live.query_name().count(), 2);
+}
+
+#[test]
+fn append_drops_components_of_dead_ids() {
+    let mut live = Ecs::new();
+    let mut level = Ecs::new();
+    let kept = level.spawn(FatEntity::new().name(CName("kept".into())));
+    level.add_name(7, CName("orphan".into()));
+    level.add_health(kept.id + 1, CHealth { hp: 1 });
+
+    let map = live.append(level);
+    assert_eq!(map.len(), 1);
+    assert_eq!(live.query_name().count(), 1);
+    assert_eq!(live.name_unwrap(map.get(kept).unwrap().id).0, "kept");
+    assert_eq!(live.query_health().count(), 0);
+}
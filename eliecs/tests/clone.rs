@@ -0,0 +1,67 @@
+mod common;
+
+use common::*;
+
+#[test]
+fn clone_entity_copies_components() {
+    let mut ecs = Ecs::new();
+    let target = ecs.spawn(FatEntity::new());
+    let e = ecs.spawn(
+        FatEntity::new()
+            .position(position(1.0, 2.0, 3.0))
+            .name(CName("orc".into()))
+            .target(CTarget { target, range: 2.0 })
+            .net_id(CNetId(7)),
+    );
+
+    let copy = ecs.clone_entity(e).unwrap();
+    assert_ne!(copy, e);
+    assert_eq!(ecs.position(copy.id), ecs.position(e.id));
+    assert_eq!(ecs.name_unwrap(copy.id).0, "orc");
+    assert_eq!(ecs.target_unwrap(copy.id).target, target);
+    // #[no_clone]
+    assert!(ecs.net_id(copy.id).is_none());
+    assert_eq!(ecs.net_id_unwrap(e.id).0, 7);
+
+    // the copy is independent of the original
+    ecs.position_mut_unwrap(copy.id).x = 10.0;
+    assert_eq!(ecs.position_unwrap(e.id).x, 1.0);
+
+    ecs.despawn(e);
+    assert_eq!(ecs.clone_entity(e), None);
+}
+
+#[test]
+fn clone_entity_into_overwrites() {
+    let mut ecs = Ecs::new();
+    let src = ecs.spawn(FatEntity::new().health(CHealth { hp: 3 }).net_id(CNetId(1)));
+    let dst = ecs.spawn(
+        FatEntity::new()
+            .health(CHealth { hp: 10 })
+            .name(CName("dst".into()))
+            .net_id(CNetId(2)),
+    );
+
+    assert!(ecs.clone_entity_into(src, dst));
+    assert_eq!(ecs.health_unwrap(dst.id).hp, 3);
+    assert_eq!(ecs.name_unwrap(dst.id).0, "dst");
+    assert_eq!(ecs.net_id_unwrap(dst.id).0, 2);
+
+    ecs.despawn(src);
+    assert!(!ecs.clone_entity_into(src, dst));
+}
+
+#[test]
+fn clone_entity_can_be_undone() {
+    let mut ecs = Ecs::new();
+    let e = ecs.spawn(FatEntity::new().health(CHealth { hp: 3 }));
+
+    ecs.begin_transaction();
+    let copy = ecs.clone_entity(e).unwrap();
+    ecs.commit();
+    assert_eq!(ecs.health_unwrap(copy.id).hp, 3);
+
+    assert!(ecs.undo());
+    assert!(!ecs.is_alive(copy));
+    assert_eq!(ecs.query_health().count(), 1);
+}
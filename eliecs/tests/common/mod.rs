@@ -0,0 +1,55 @@
+#![allow(dead_code)]
+
+use eliecs::{components, EntityMap, MapEntities};
+use serde::{Deserialize, Serialize};
+
+components! {
+    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+    #[replicate]
+    pub struct CPosition {
+        pub x: f32,
+        pub y: f32,
+        pub z: f32,
+    }
+    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+    #[replicate]
+    pub struct CName(pub String);
+
+    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+    pub struct CHealth {
+        pub hp: i32,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+    #[replicate]
+    pub struct CTarget {
+        #[entity]
+        pub target: Entity,
+        pub range: f32,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+    pub struct CParent(#[entity] pub Option<Entity>);
+
+    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+    #[map_entities]
+    pub struct CInventory {
+        pub items: Vec<(Entity, u32)>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+    #[no_clone]
+    pub struct CNetId(pub u64);
+}
+
+impl MapEntities for CInventory {
+    fn map_entities(&mut self, map: &EntityMap) {
+        for (item, _) in &mut self.items {
+            item.map_entities(map);
+        }
+    }
+}
+
+pub fn position(x: f32, y: f32, z: f32) -> CPosition {
+    CPosition { x, y, z }
+}
@@ -0,0 +1,110 @@
+mod common;
+
+use common::*;
+use eliecs::Entity;
+
+fn world() -> (Ecs, Vec<Entity>) {
+    let mut ecs = Ecs::new();
+    let entities = (0..6)
+        .map(|i| {
+            ecs.spawn(
+                FatEntity::new()
+                    .position(position(i as f32, 0.0, 0.0))
+                    .health(CHealth { hp: i }),
+            )
+        })
+        .collect();
+    (ecs, entities)
+}
+
+fn assert_round_trip(a: &Ecs, b: &Ecs) -> WorldDelta {
+    let delta = Ecs::diff(a, b);
+    let json = serde_json::to_string(&delta).unwrap();
+    let delta: WorldDelta = serde_json::from_str(&json).unwrap();
+
+    let mut patched = a.clone();
+    patched.apply_delta(&delta);
+    assert!(patched == *b, "patched world differs:\n{delta:#?}");
+    delta
+}
+
+#[test]
+fn identical_worlds_have_empty_delta() {
+    let (a, _) = world();
+    assert!(assert_round_trip(&a, &a.clone()).is_empty());
+}
+
+#[test]
+fn component_changes() {
+    let (a, e) = world();
+    let b = a.clone();
+    b.health_mut_unwrap(e[0].id).hp = 100;
+    b.remove_position(e[1].id);
+    b.add_name(e[2].id, CName("two".into()));
+
+    let delta = assert_round_trip(&a, &b);
+    assert_eq!(
+        delta.updated,
+        vec![(e[0], ComponentTypeContaining::CHealth(CHealth { hp: 100 }))]
+    );
+    assert_eq!(delta.removed, vec![(e[1], ComponentType::CPosition)]);
+    assert_eq!(delta.inserted.len(), 1);
+    assert!(delta.spawned.is_empty() && delta.despawned.is_empty());
+    assert!(delta.free_list.is_none());
+}
+
+#[test]
+fn spawns_and_despawns() {
+    let (a, e) = world();
+    let mut b = a.clone();
+    b.despawn(e[3]);
+    b.despawn(e[5]);
+    let respawned = b.spawn(FatEntity::new().name(CName("reused".into())));
+    let fresh = b.spawn(FatEntity::new());
+    let fresh2 = b.spawn(FatEntity::new().health(CHealth { hp: -1 }));
+
+    let delta = assert_round_trip(&a, &b);
+    assert_eq!(delta.despawned, vec![e[3], e[5]]);
+    assert_eq!(delta.spawned, vec![fresh, respawned, fresh2]);
+
+    // and back again
+    assert_round_trip(&b, &a);
+}
+
+#[test]
+fn ignores_components_of_dead_ids() {
+    let (a, e) = world();
+    let mut b = a.clone();
+    b.despawn(e[5]);
+    b.add_name(e[5].id, CName("orphan".into()));
+    b.add_name(40, CName("nobody".into()));
+
+    let delta = Ecs::diff(&a, &b);
+    assert_eq!(delta.despawned, vec![e[5]]);
+    assert!(delta.inserted.is_empty() && delta.updated.is_empty());
+    assert!(Ecs::diff(&b, &a).removed.is_empty());
+}
+
+mod without_eq {
+    use eliecs::components;
+    use serde::{Deserialize, Serialize};
+
+    components! {
+        #[derive(Debug, Serialize, Deserialize, Clone)]
+        pub struct CLabel(pub String);
+    }
+
+    #[test]
+    fn diffs_components_without_partial_eq() {
+        let mut a = Ecs::new();
+        let e = a.spawn(FatEntity::new().label(CLabel("a".into())));
+        let b = a.clone();
+        assert!(Ecs::diff(&a, &b).is_empty());
+
+        b.label_mut_unwrap(e.id).0 = "b".into();
+        let delta = Ecs::diff(&a, &b);
+        assert_eq!(delta.updated.len(), 1);
+        a.apply_delta(&delta);
+        assert_eq!(a.label(e.id).unwrap().0, "b");
+    }
+}
@@ -0,0 +1,71 @@
+mod common;
+
+use common::*;
+use eliecs::Table;
+
+fn world() -> Ecs {
+    let mut ecs = Ecs::new();
+    ecs.spawn(
+        FatEntity::new()
+            .name(CName("a".into()))
+            .health(CHealth { hp: 3 }),
+    );
+    let gone = ecs.spawn(FatEntity::new());
+    ecs.spawn(FatEntity::new());
+    ecs.spawn(FatEntity::new().name(CName("b".into())));
+    ecs.despawn(gone);
+    ecs
+}
+
+#[test]
+fn debug_lists_entities_and_components() {
+    let ecs = world();
+    assert_eq!(
+        format!("{ecs:?}"),
+        r#"Ecs {0v1: CHealth { hp: 3 }, CName("a"), 2v1: (), 3v1: CName("b")}"#
+    );
+    assert_eq!(
+        format!("{ecs:#?}"),
+        r#"Ecs {
+    0v1: CHealth { hp: 3 }, CName("a"),
+    2v1: (),
+    3v1: CName("b"),
+}"#
+    );
+    assert_eq!(format!("{:?}", Ecs::new()), "Ecs {}");
+}
+
+#[test]
+fn display_has_a_line_per_entity() {
+    assert_eq!(
+        world().to_string(),
+        "0v1: CHealth { hp: 3 }, CName(\"a\")\n2v1: ()\n3v1: CName(\"b\")\n"
+    );
+}
+
+#[test]
+fn dump_table() {
+    let ecs = world();
+    assert_eq!(
+        ecs.dump_table(&[ComponentType::CName, ComponentType::CHealth], 10)
+            .to_string(),
+        "\
+entity  Name        Health
+0v1     CName(\"a\")  CHealth { hp: 3 }
+3v1     CName(\"b\")  -
+"
+    );
+    assert_eq!(
+        ecs.dump_table(&[], 2).to_string(),
+        "entity\n0v1\n2v1\n... 1 more\n"
+    );
+}
+
+#[test]
+fn long_cells_are_cut() {
+    let mut table = Table::new(["a", "b"]);
+    table.max_width = 5;
+    table.push(["short", "much too long"]);
+    table.push(["x", "y"]);
+    assert_eq!(table.to_string(), "a      b\nshort  much…\nx      y\n");
+}
@@ -0,0 +1,60 @@
+mod common;
+
+use common::*;
+
+#[test]
+fn dynamic_access_matches_static_access() {
+    let mut ecs = Ecs::new();
+    let e = ecs.spawn(FatEntity::new().health(CHealth { hp: 10 }));
+
+    assert!(ecs.has_dyn(e.id, ComponentType::CHealth));
+    assert!(!ecs.has_dyn(e.id, ComponentType::CName));
+    assert_eq!(
+        ecs.get_dyn(e.id, ComponentType::CHealth),
+        Some(ComponentTypeContaining::CHealth(CHealth { hp: 10 }))
+    );
+    assert_eq!(ecs.get_dyn(e.id, ComponentType::CName), None);
+
+    assert!(!ecs.insert_dyn(e.id, ComponentTypeContaining::CName(CName("orc".into()))));
+    assert!(ecs.insert_dyn(e.id, ComponentTypeContaining::CHealth(CHealth { hp: 3 })));
+    assert_eq!(ecs.name_unwrap(e.id).0, "orc");
+    assert_eq!(ecs.health_unwrap(e.id).hp, 3);
+
+    assert!(ecs.remove_dyn(e.id, ComponentType::CHealth));
+    assert!(!ecs.remove_dyn(e.id, ComponentType::CHealth));
+    assert!(ecs.health(e.id).is_none());
+}
+
+#[test]
+fn generic_copy_over_all_types() {
+    let mut ecs = Ecs::new();
+    let a = ecs.spawn(
+        FatEntity::new()
+            .health(CHealth { hp: 10 })
+            .position(position(1.0, 2.0, 3.0)),
+    );
+    let b = ecs.spawn(FatEntity::new());
+    for ty in ComponentType::ALL {
+        if let Some(c) = ecs.get_dyn(a.id, ty) {
+            ecs.insert_dyn(b.id, c);
+        }
+    }
+    assert_eq!(ecs.snapshot_entity(a), ecs.snapshot_entity(b));
+}
+
+#[test]
+fn dynamic_changes_are_journaled() {
+    let mut ecs = Ecs::new();
+    let e = ecs.spawn(FatEntity::new());
+    ecs.start_journal(false);
+    ecs.insert_dyn(e.id, ComponentTypeContaining::CHealth(CHealth { hp: 1 }));
+    ecs.remove_dyn(e.id, ComponentType::CHealth);
+    let journal = ecs.take_journal().unwrap();
+    assert_eq!(
+        journal.records,
+        [
+            Mutation::Insert(e, ComponentTypeContaining::CHealth(CHealth { hp: 1 })),
+            Mutation::Remove(e, ComponentType::CHealth),
+        ]
+    );
+}
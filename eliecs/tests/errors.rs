@@ -0,0 +1,104 @@
+mod common;
+
+use common::*;
+use eliecs::EcsError;
+
+#[test]
+fn try_accessors() {
+    let mut ecs = Ecs::new();
+    let e = ecs.spawn(FatEntity::new().health(CHealth { hp: 3 }));
+
+    assert_eq!(*ecs.try_health(e).unwrap(), CHealth { hp: 3 });
+    ecs.try_health_mut(e).unwrap().hp = 4;
+    assert_eq!(ecs.health(e.id), Some(&CHealth { hp: 4 }));
+    assert_eq!(
+        ecs.try_name(e).unwrap_err(),
+        EcsError::MissingComponent {
+            entity: e,
+            component: "CName"
+        }
+    );
+
+    assert_eq!(ecs.try_add_name(e, CName("a".into())), Ok(false));
+    assert_eq!(ecs.try_add_name(e, CName("b".into())), Ok(true));
+    assert_eq!(ecs.try_remove_name(e), Ok(CName("b".into())));
+    assert_eq!(
+        ecs.try_remove_name(e).unwrap_err().to_string(),
+        "entity 0v1 doesn't have component CName"
+    );
+}
+
+#[test]
+fn dead_entities() {
+    let mut ecs = Ecs::new();
+    let e = ecs.spawn(FatEntity::new().health(CHealth { hp: 3 }));
+    let fat = ecs.try_despawn(e).unwrap();
+    assert_eq!(fat.health, Some(CHealth { hp: 3 }));
+    assert_eq!(ecs.try_despawn(e).unwrap_err(), EcsError::DeadEntity(e));
+
+    // the id is reused, but the old handle stays dead
+    let new = ecs
+        .try_spawn(FatEntity::new().health(CHealth { hp: 5 }))
+        .unwrap();
+    assert_eq!(new.id, e.id);
+    assert_eq!(ecs.try_health(e).unwrap_err(), EcsError::DeadEntity(e));
+    assert_eq!(ecs.try_health_mut(e).unwrap_err(), EcsError::DeadEntity(e));
+    assert_eq!(
+        ecs.try_add_health(e, CHealth { hp: 0 }),
+        Err(EcsError::DeadEntity(e))
+    );
+    assert_eq!(ecs.try_remove_health(e), Err(EcsError::DeadEntity(e)));
+    assert_eq!(ecs.health(new.id), Some(&CHealth { hp: 5 }));
+    assert_eq!(
+        EcsError::DeadEntity(e).to_string(),
+        "entity 0v1 isn't alive"
+    );
+}
+
+#[test]
+fn try_remove_is_undoable() {
+    let mut ecs = Ecs::new();
+    let e = ecs.spawn(FatEntity::new().name(CName("a".into())));
+    ecs.begin_transaction();
+    ecs.try_remove_name(e).unwrap();
+    ecs.commit();
+    assert!(ecs.undo());
+    assert_eq!(ecs.name(e.id), Some(&CName("a".into())));
+}
+
+#[test]
+fn borrow_conflicts() {
+    let mut ecs = Ecs::new();
+    let a = ecs.spawn(FatEntity::new().health(CHealth { hp: 1 }));
+    let b = ecs.spawn(FatEntity::new().health(CHealth { hp: 2 }));
+    let conflict = |entity| EcsError::BorrowConflict {
+        entity,
+        component: "CHealth",
+    };
+
+    let mut hp = ecs.try_health_mut(a).unwrap();
+    assert_eq!(ecs.try_health(a).unwrap_err(), conflict(a));
+    assert_eq!(ecs.try_health_mut(a).unwrap_err(), conflict(a));
+    // other entities and components are free
+    ecs.try_health_mut(b).unwrap().hp = 3;
+    ecs.try_add_name(a, CName("a".into())).unwrap();
+    // adding or removing could move `hp`
+    assert_eq!(
+        ecs.try_add_health(b, CHealth { hp: 0 }).unwrap_err(),
+        conflict(b)
+    );
+    assert_eq!(ecs.try_remove_health(b).unwrap_err(), conflict(b));
+    hp.hp = 5;
+    drop(hp);
+
+    {
+        let (x, y) = (ecs.try_health(a).unwrap(), ecs.try_health(a).unwrap());
+        assert_eq!(x.hp + y.hp, 10);
+        assert_eq!(ecs.try_health_mut(a).unwrap_err(), conflict(a));
+    }
+    assert_eq!(ecs.try_remove_health(a), Ok(CHealth { hp: 5 }));
+    assert_eq!(
+        conflict(a).to_string(),
+        "component CHealth of entity 0v1 is already borrowed"
+    );
+}
@@ -0,0 +1,57 @@
+mod common;
+
+use common::*;
+
+fn orc() -> FatEntity {
+    FatEntity::new()
+        .position(position(1.0, 2.0, 3.0))
+        .health(CHealth { hp: 10 })
+        .name(CName("orc".into()))
+}
+
+#[test]
+fn snapshot_entity_clones_components() {
+    let mut ecs = Ecs::new();
+    let e = ecs.spawn(orc());
+    assert_eq!(ecs.snapshot_entity(e), orc());
+    assert_eq!(
+        ecs.components_of(e),
+        [
+            ComponentType::CHealth,
+            ComponentType::CName,
+            ComponentType::CPosition
+        ]
+    );
+
+    ecs.despawn(e);
+    assert_eq!(ecs.snapshot_entity(e), FatEntity::new());
+    assert!(ecs.components_of(e).is_empty());
+}
+
+#[test]
+fn take_moves_entities_between_worlds() {
+    let mut a = Ecs::new();
+    let mut b = Ecs::new();
+    let keep = a.spawn(FatEntity::new().health(CHealth { hp: 1 }));
+    let e = a.spawn(orc());
+
+    let taken = a.take(e).unwrap();
+    assert!(!a.is_alive(e));
+    assert_eq!(a.take(e), None);
+    assert_eq!(a.query_name().count(), 0);
+    assert_eq!(a.health_unwrap(keep.id).hp, 1);
+
+    let moved = b.spawn(taken);
+    assert_eq!(b.snapshot_entity(moved), orc());
+}
+
+#[test]
+fn take_can_be_undone() {
+    let mut ecs = Ecs::new();
+    let e = ecs.spawn(orc());
+    ecs.begin_transaction();
+    ecs.take(e);
+    ecs.commit();
+    assert!(ecs.undo());
+    assert_eq!(ecs.snapshot_entity(e), orc());
+}
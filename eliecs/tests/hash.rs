@@ -0,0 +1,105 @@
+mod common;
+
+use common::*;
+
+#[test]
+fn hash_ignores_dense_order() {
+    let mut a = Ecs::new();
+    let mut b = Ecs::new();
+    let ea = [a.spawn(FatEntity::new()), a.spawn(FatEntity::new())];
+    let eb = [b.spawn(FatEntity::new()), b.spawn(FatEntity::new())];
+
+    a.add_health(ea[0].id, CHealth { hp: 1 });
+    a.add_health(ea[1].id, CHealth { hp: 2 });
+    b.add_health(eb[1].id, CHealth { hp: 2 });
+    b.add_health(eb[0].id, CHealth { hp: 1 });
+    assert!(a == b);
+    assert_eq!(a.state_hash().unwrap(), b.state_hash().unwrap());
+
+    // -0.0 == 0.0, so they shouldn't desync either
+    a.add_position(ea[0].id, position(0.0, 1.0, 2.0));
+    b.add_position(eb[0].id, position(-0.0, 1.0, 2.0));
+    assert_eq!(a.state_hash().unwrap(), b.state_hash().unwrap());
+}
+
+#[test]
+fn breakdown_finds_desynced_pool() {
+    let mut a = Ecs::new();
+    let e = a.spawn(
+        FatEntity::new()
+            .health(CHealth { hp: 3 })
+            .name(CName("a".into())),
+    );
+    let b = a.clone();
+    assert_eq!(a.state_hashes().unwrap(), b.state_hashes().unwrap());
+
+    b.health_mut_unwrap(e.id).hp = 4;
+    assert_ne!(a.state_hash().unwrap(), b.state_hash().unwrap());
+    let (ha, hb) = (a.state_hashes().unwrap(), b.state_hashes().unwrap());
+    assert_eq!(ha.desynced_components(&hb), vec![ComponentType::CHealth]);
+    assert_eq!(ha.existence, hb.existence);
+    assert_eq!(ha.free_list, hb.free_list);
+}
+
+#[test]
+fn free_list_order_matters() {
+    let mut a = Ecs::new();
+    let e = [a.spawn(FatEntity::new()), a.spawn(FatEntity::new())];
+    let mut b = a.clone();
+    a.despawn(e[0]);
+    a.despawn(e[1]);
+    b.despawn(e[1]);
+    b.despawn(e[0]);
+
+    let (ha, hb) = (a.state_hashes().unwrap(), b.state_hashes().unwrap());
+    assert_eq!(ha.existence, hb.existence);
+    assert_ne!(ha.free_list, hb.free_list);
+    assert!(ha.desynced_components(&hb).is_empty());
+}
+
+#[test]
+fn hash_is_pinned() {
+    // must never change, peers on different builds compare these
+    assert_eq!(
+        eliecs::hash::stable_hash(&(1u32, "a", Some(1.5f32))).unwrap(),
+        eliecs::hash::stable_hash(&(1u64, "a", Some(1.5f64))).unwrap()
+    );
+    assert_eq!(eliecs::hash::stable_hash(&0u8).unwrap(), 0xeba8d4f0aba80485);
+}
+
+#[test]
+fn maps_hash_the_same_in_any_order() {
+    use std::collections::{BTreeMap, HashMap};
+
+    let pairs = (0..64).map(|i| (i, i * 2)).collect::<Vec<_>>();
+    let ordered = pairs.iter().copied().collect::<BTreeMap<u32, u32>>();
+    let reversed = pairs.iter().rev().copied().collect::<HashMap<u32, u32>>();
+    assert_eq!(
+        eliecs::hash::stable_hash(&ordered).unwrap(),
+        eliecs::hash::stable_hash(&reversed).unwrap()
+    );
+
+    // keys and values still stay paired up
+    let swapped = pairs
+        .iter()
+        .map(|&(k, v)| (v, k))
+        .collect::<BTreeMap<_, _>>();
+    assert_ne!(
+        eliecs::hash::stable_hash(&ordered).unwrap(),
+        eliecs::hash::stable_hash(&swapped).unwrap()
+    );
+}
+
+#[test]
+fn serialize_errors_are_returned() {
+    let poisoned = std::sync::Arc::new(std::sync::Mutex::new(1));
+    let clone = poisoned.clone();
+    let _ = std::thread::spawn(move || {
+        let _guard = clone.lock().unwrap();
+        panic!("poisoning the lock");
+    })
+    .join();
+
+    let err = eliecs::hash::stable_hash(&*poisoned).unwrap_err();
+    assert!(err.to_string().starts_with("couldn't hash: "), "{err}");
+}
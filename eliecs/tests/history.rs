@@ -0,0 +1,155 @@
+mod common;
+
+use common::*;
+
+#[test]
+fn undo_and_redo_transactions() {
+    let mut ecs = Ecs::new();
+    let a = ecs.spawn(
+        FatEntity::new()
+            .health(CHealth { hp: 10 })
+            .name(CName("a".into())),
+    );
+    let before = ecs.snapshot();
+
+    ecs.begin_transaction();
+    let b = ecs.spawn(FatEntity::new().position(position(1.0, 1.0, 1.0)));
+    ecs.health_mut_unwrap(a.id).hp = 5;
+    ecs.add_name(a.id, CName("renamed".into()));
+    ecs.remove_health(a.id);
+    ecs.add_target(
+        b.id,
+        CTarget {
+            target: a,
+            range: 1.0,
+        },
+    );
+    assert!(ecs.commit());
+    let after = ecs.snapshot();
+
+    ecs.begin_transaction();
+    ecs.despawn(a);
+    assert!(ecs.commit());
+    let despawned = ecs.snapshot();
+
+    assert!(ecs.undo());
+    // the same entity is back, so handles held elsewhere still work
+    assert!(ecs.is_alive(a));
+    assert!(ecs == after);
+    assert_eq!(ecs.target_unwrap(b.id).target, a);
+
+    assert!(ecs.undo());
+    assert!(!ecs.is_alive(b));
+    assert_eq!(ecs.health_unwrap(a.id).hp, 10);
+    assert_eq!(ecs.name_unwrap(a.id).0, "a");
+    assert!(!ecs.undo());
+
+    assert!(ecs.redo());
+    assert!(ecs.is_alive(b));
+    assert_eq!(ecs.name_unwrap(a.id).0, "renamed");
+    assert!(ecs.health(a.id).is_none());
+    assert!(ecs.redo());
+    assert!(!ecs.is_alive(a));
+    assert_eq!(ecs.query_name().count(), despawned.query_name().count());
+    assert!(!ecs.redo());
+
+    // undoing everything gets the original world back, apart from the free list
+    while ecs.undo() {}
+    assert_eq!(
+        ecs.to_scene().entities.len(),
+        before.to_scene().entities.len()
+    );
+    assert_eq!(ecs.health_unwrap(a.id), before.health_unwrap(a.id));
+}
+
+#[test]
+fn nested_transactions_undo_as_one() {
+    let mut ecs = Ecs::new();
+    ecs.begin_transaction();
+    let a = ecs.spawn(FatEntity::new());
+    ecs.begin_transaction();
+    ecs.add_health(a.id, CHealth { hp: 1 });
+    assert!(!ecs.commit());
+    assert!(ecs.in_transaction());
+    assert!(ecs.commit());
+    assert_eq!(ecs.history().undo_len(), 1);
+
+    // new work clears the redo stack
+    ecs.undo();
+    assert!(ecs.history().can_redo());
+    ecs.begin_transaction();
+    ecs.spawn(FatEntity::new());
+    ecs.commit();
+    assert!(!ecs.history().can_redo());
+
+    // empty transactions aren't kept
+    ecs.begin_transaction();
+    assert!(!ecs.commit());
+    assert_eq!(ecs.history().undo_len(), 1);
+}
+
+#[test]
+fn changes_outside_transactions_clear_redo() {
+    let mut ecs = Ecs::new();
+    ecs.begin_transaction();
+    let e = ecs.spawn(FatEntity::new().name(CName("e".into())));
+    ecs.commit();
+
+    ecs.undo();
+    let f = ecs.spawn(FatEntity::new().name(CName("f".into())));
+    assert_eq!((f.id, f.version.get()), (e.id, 2));
+    assert!(!ecs.history().can_redo());
+    assert!(!ecs.redo());
+    assert!(ecs.is_alive(f) && !ecs.is_alive(e));
+    assert_eq!(ecs.name_unwrap(f.id).0, "f");
+
+    // undoing and redoing themselves keep the stacks
+    for hp in 0..2 {
+        ecs.begin_transaction();
+        ecs.add_health(f.id, CHealth { hp });
+        ecs.commit();
+    }
+    assert!(ecs.undo() && ecs.undo());
+    assert!(ecs.redo());
+    assert_eq!(ecs.history().redo_len(), 1);
+}
+
+#[test]
+fn replaying_a_spawn_leaves_a_taken_id_alone() {
+    let mut ecs = Ecs::new();
+    let e = ecs.spawn(FatEntity::new());
+    ecs.despawn(e);
+    let f = ecs.spawn(FatEntity::new().health(CHealth { hp: 1 }));
+
+    ecs.apply_mutation(&eliecs::Mutation::Spawn(e));
+    assert!(ecs.is_alive(f) && !ecs.is_alive(e));
+    assert_eq!(ecs.health_unwrap(f.id).hp, 1);
+}
+
+#[test]
+fn undo_refuses_when_changes_since_get_in_the_way() {
+    let mut ecs = Ecs::new();
+    let e = ecs.spawn(FatEntity::new().name(CName("e".into())));
+    ecs.begin_transaction();
+    ecs.despawn(e);
+    ecs.commit();
+
+    // outside any transaction, so the undo stack stays
+    let f = ecs.spawn(FatEntity::new());
+    assert_eq!(f.id, e.id);
+    assert!(!ecs.undo());
+    assert!(ecs.is_alive(f) && !ecs.is_alive(e));
+    assert_eq!(ecs.history().undo_len(), 1);
+
+    ecs.despawn(f);
+    assert!(ecs.undo());
+    assert_eq!(ecs.name_unwrap(e.id).0, "e");
+
+    // a transaction writing to an entity despawned since can't be undone either
+    ecs.begin_transaction();
+    ecs.name_mut_unwrap(e.id).0 = "renamed".into();
+    ecs.commit();
+    ecs.despawn(e);
+    assert!(!ecs.undo());
+    assert_eq!(ecs.history().undo_len(), 1);
+}
@@ -0,0 +1,329 @@
+mod common;
+
+use std::{
+    io::{BufRead, BufReader, ErrorKind, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+use common::*;
+use eliecs::{inspector::Inspector, Entity};
+use serde_json::{json, Value as Json};
+
+/// One client talking to an inspector, polled by the test instead of a game loop.
+struct Session {
+    inspector: Inspector<Ecs>,
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    line: String,
+}
+
+impl Session {
+    fn new() -> Self {
+        let inspector = Inspector::bind("127.0.0.1:0").unwrap();
+        let writer = TcpStream::connect(inspector.local_addr().unwrap()).unwrap();
+        writer
+            .set_read_timeout(Some(Duration::from_millis(5)))
+            .unwrap();
+        Self {
+            inspector,
+            reader: BufReader::new(writer.try_clone().unwrap()),
+            writer,
+            line: String::new(),
+        }
+    }
+
+    fn send(&mut self, line: &str) {
+        writeln!(self.writer, "{line}").unwrap();
+    }
+
+    /// Polls until the inspector sends a line.
+    fn receive(&mut self, ecs: &mut Ecs) -> Json {
+        for _ in 0..1000 {
+            self.inspector.poll(ecs).unwrap();
+            match self.reader.read_line(&mut self.line) {
+                Ok(_) if self.line.ends_with('\n') => {
+                    let json = serde_json::from_str(&self.line).unwrap();
+                    self.line.clear();
+                    return json;
+                }
+                Ok(_) => {}
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                Err(e) => panic!("{e}"),
+            }
+        }
+        panic!("the inspector didn't answer");
+    }
+
+    fn request(&mut self, ecs: &mut Ecs, request: Json) -> Json {
+        self.send(&request.to_string());
+        self.receive(ecs)
+    }
+
+    fn ok(&mut self, ecs: &mut Ecs, request: Json) -> Json {
+        let reply = self.request(ecs, request);
+        assert!(reply.get("error").is_none(), "{reply}");
+        reply["ok"].clone()
+    }
+
+    fn error(&mut self, ecs: &mut Ecs, request: Json) -> String {
+        let reply = self.request(ecs, request);
+        reply["error"].as_str().unwrap().to_string()
+    }
+}
+
+fn world() -> (Ecs, Entity, Entity) {
+    let mut ecs = Ecs::new();
+    let a = ecs.spawn(
+        FatEntity::new()
+            .position(position(1.0, 2.0, 3.0))
+            .name(CName("a".into())),
+    );
+    let b = ecs.spawn(FatEntity::new().health(CHealth { hp: 10 }));
+    (ecs, a, b)
+}
+
+#[test]
+fn looks_into_the_world() {
+    let (mut ecs, a, b) = world();
+    let mut session = Session::new();
+
+    let reply = session.request(&mut ecs, json!({"id": 7, "cmd": "entities"}));
+    assert_eq!(reply, json!({"id": 7, "ok": [a, b]}));
+    assert_eq!(
+        session.ok(
+            &mut ecs,
+            json!({"cmd": "entities", "query": "health where health.hp > 5"})
+        ),
+        json!([b])
+    );
+    assert_eq!(
+        session.ok(&mut ecs, json!({"cmd": "components", "entity": a})),
+        json!({"Name": "a", "Position": {"x": 1.0, "y": 2.0, "z": 3.0}})
+    );
+    assert_eq!(
+        session.ok(
+            &mut ecs,
+            json!({"cmd": "get", "entity": a, "component": "CPosition", "path": "y"})
+        ),
+        json!(2.0)
+    );
+    let schema = session.ok(&mut ecs, json!({"cmd": "schema"}));
+    assert_eq!(
+        serde_json::from_value::<eliecs::schema::Schema>(schema).unwrap(),
+        Ecs::schema()
+    );
+}
+
+#[test]
+fn edits_the_world() {
+    let (mut ecs, a, b) = world();
+    let mut session = Session::new();
+
+    session.ok(
+        &mut ecs,
+        json!({"cmd": "set", "entity": a, "component": "position", "path": "x", "value": 5}),
+    );
+    assert_eq!(ecs.position(a.id).unwrap().x, 5.0);
+
+    session.ok(
+        &mut ecs,
+        json!({
+            "cmd": "insert",
+            "entity": b,
+            "component": "Target",
+            "value": {"target": a, "range": 2.5},
+        }),
+    );
+    assert_eq!(
+        ecs.target(b.id),
+        Some(&CTarget {
+            target: a,
+            range: 2.5
+        })
+    );
+    assert_eq!(
+        session.ok(
+            &mut ecs,
+            json!({"cmd": "remove", "entity": a, "component": "Name"})
+        ),
+        json!(true)
+    );
+    assert_eq!(ecs.name(a.id), None);
+
+    let spawned = session.ok(
+        &mut ecs,
+        json!({"cmd": "spawn", "components": {"Name": "c", "Health": {"hp": 1}}}),
+    );
+    let c: Entity = serde_json::from_value(spawned).unwrap();
+    assert_eq!(ecs.name(c.id), Some(&CName("c".into())));
+    assert_eq!(ecs.health(c.id), Some(&CHealth { hp: 1 }));
+
+    assert_eq!(
+        session.ok(&mut ecs, json!({"cmd": "despawn", "entity": c})),
+        json!(true)
+    );
+    assert!(!ecs.is_alive(c));
+}
+
+#[test]
+fn reports_errors() {
+    let (mut ecs, a, b) = world();
+    let mut session = Session::new();
+    ecs.despawn(b);
+
+    assert_eq!(
+        session.error(&mut ecs, json!({"cmd": "components", "entity": b})),
+        "1v1 isn't alive"
+    );
+    assert_eq!(
+        session.error(
+            &mut ecs,
+            json!({"cmd": "insert", "entity": a, "component": "Speed", "value": 1})
+        ),
+        "no component `Speed`"
+    );
+    let before = ecs.snapshot();
+    session.error(
+        &mut ecs,
+        json!({"cmd": "spawn", "components": {"Name": "c", "Health": {"hp": "lots"}}}),
+    );
+    assert!(ecs == before);
+
+    session.send("not json");
+    assert_eq!(session.receive(&mut ecs)["id"], Json::Null);
+    let reply = session.request(&mut ecs, json!({"id": "x", "cmd": "explode"}));
+    assert_eq!(reply["id"], "x");
+    assert!(reply["error"].as_str().unwrap().contains("explode"));
+}
+
+#[test]
+fn notifies_subscribers() {
+    let (mut ecs, a, b) = world();
+    let mut session = Session::new();
+    session.ok(&mut ecs, json!({"cmd": "subscribe"}));
+
+    ecs.position_mut(a.id).unwrap().x = 9.0;
+    assert_eq!(
+        session.receive(&mut ecs),
+        json!({
+            "event": "changed",
+            "entity": a,
+            "component": "Position",
+            "value": {"x": 9.0, "y": 2.0, "z": 3.0},
+        })
+    );
+
+    ecs.despawn(b);
+    assert_eq!(
+        session.receive(&mut ecs),
+        json!({"event": "despawned", "entity": b})
+    );
+
+    session.ok(
+        &mut ecs,
+        json!({"cmd": "subscribe", "components": ["name"]}),
+    );
+    ecs.position_mut(a.id).unwrap().x = 10.0;
+    ecs.remove_name(a.id);
+    assert_eq!(
+        session.receive(&mut ecs),
+        json!({"event": "removed", "entity": a, "component": "Name"})
+    );
+
+    session.ok(&mut ecs, json!({"cmd": "unsubscribe"}));
+    ecs.add_name(a.id, CName("again".into()));
+    assert_eq!(session.ok(&mut ecs, json!({"cmd": "entities"})), json!([a]));
+}
+
+#[test]
+fn notifies_about_requested_changes() {
+    let (mut ecs, a, _) = world();
+    let mut session = Session::new();
+    session.ok(
+        &mut ecs,
+        json!({"cmd": "subscribe", "components": ["Name"]}),
+    );
+
+    // the reply and the event come out of the same poll
+    session.send(&json!({"cmd": "remove", "entity": a, "component": "Name"}).to_string());
+    assert_eq!(session.receive(&mut ecs)["ok"], json!(true));
+    let mut line = String::new();
+    for _ in 0..200 {
+        if session.reader.read_line(&mut line).is_ok() && line.ends_with('\n') {
+            break;
+        }
+    }
+    assert_eq!(
+        serde_json::from_str::<Json>(&line).unwrap(),
+        json!({"event": "removed", "entity": a, "component": "Name"})
+    );
+}
+
+#[test]
+fn drops_clients_sending_long_lines() {
+    let (mut ecs, _, _) = world();
+    let mut session = Session::new();
+    session.ok(&mut ecs, json!({"cmd": "schema"}));
+    assert_eq!(session.inspector.clients(), 1);
+
+    let chunk = vec![b'x'; 64 * 1024];
+    for _ in 0..1000 {
+        // the inspector stops reading once it drops us, so writing may fail
+        if session.writer.write_all(&chunk).is_err() {
+            break;
+        }
+        session.inspector.poll(&mut ecs).unwrap();
+        if session.inspector.clients() == 0 {
+            break;
+        }
+    }
+    assert_eq!(session.inspector.clients(), 0);
+}
+
+#[test]
+fn answers_a_limited_number_of_requests_per_poll() {
+    let (mut ecs, _, _) = world();
+    let mut session = Session::new();
+    let requests = (0..100)
+        .map(|id| json!({"id": id, "cmd": "entities"}).to_string() + "\n")
+        .collect::<String>();
+    session.writer.write_all(requests.as_bytes()).unwrap();
+
+    session.inspector.poll(&mut ecs).unwrap();
+    let mut ids = Vec::new();
+    loop {
+        match session.reader.read_line(&mut session.line) {
+            Ok(_) if session.line.ends_with('\n') => {
+                let reply: Json = serde_json::from_str(&session.line).unwrap();
+                ids.push(reply["id"].as_u64().unwrap());
+                session.line.clear();
+            }
+            Ok(_) => {}
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => break,
+            Err(e) => panic!("{e}"),
+        }
+    }
+    assert_eq!(ids, (0..64).collect::<Vec<_>>());
+    assert_eq!(session.receive(&mut ecs)["id"], 64);
+}
+
+#[test]
+fn drops_clients_not_reading_replies() {
+    let (mut ecs, _, _) = world();
+    let mut session = Session::new();
+    session.ok(&mut ecs, json!({"cmd": "schema"}));
+
+    let requests = "{\"cmd\": \"schema\"}\n".repeat(64);
+    for _ in 0..10_000 {
+        // the inspector stops reading once it drops us, so writing may fail
+        if session.writer.write_all(requests.as_bytes()).is_err() {
+            break;
+        }
+        session.inspector.poll(&mut ecs).unwrap();
+        if session.inspector.clients() == 0 {
+            break;
+        }
+    }
+    assert_eq!(session.inspector.clients(), 0);
+}
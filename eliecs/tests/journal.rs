@@ -0,0 +1,99 @@
+mod common;
+
+use common::*;
+
+#[test]
+fn replay_reconstructs_every_step() {
+    let mut ecs = Ecs::new();
+    let a = ecs.spawn(FatEntity::new().health(CHealth { hp: 10 }));
+    let initial = ecs.snapshot();
+    ecs.start_journal(true);
+
+    let b = ecs.spawn(FatEntity::new().name(CName("b".into())));
+    ecs.add_position(a.id, position(1.0, 2.0, 3.0));
+    ecs.health_mut_unwrap(a.id).hp -= 4;
+    ecs.remove_name(b.id);
+    for (_, p) in ecs.query_position_mut() {
+        p.x = 7.0;
+    }
+    ecs.despawn(a);
+    let c = ecs.spawn(FatEntity::new().health(CHealth { hp: 1 }));
+    assert_eq!(c.id, a.id);
+
+    let final_state = ecs.snapshot();
+    let journal = ecs.take_journal().unwrap();
+    assert!(!ecs.is_journaling());
+
+    let json = serde_json::to_string(&journal).unwrap();
+    let journal: Journal = serde_json::from_str(&json).unwrap();
+    assert!(Ecs::replay(&initial, &journal, journal.len()) == final_state);
+    assert!(Ecs::replay(&initial, &journal, 0) == initial);
+
+    assert_eq!(
+        journal.records[..3],
+        [
+            Mutation::Spawn(b),
+            Mutation::Insert(b, ComponentTypeContaining::CName(CName("b".into()))),
+            Mutation::Insert(
+                a,
+                ComponentTypeContaining::CPosition(position(1.0, 2.0, 3.0))
+            ),
+        ]
+    );
+    // the write through `health_mut_unwrap` shows up before the next mutation
+    assert_eq!(
+        journal.records[3..5],
+        [
+            Mutation::Insert(a, ComponentTypeContaining::CHealth(CHealth { hp: 6 })),
+            Mutation::Remove(b, ComponentType::CName),
+        ]
+    );
+
+    let mut ecs = initial.clone();
+    ecs.spawn(FatEntity::new().name(CName("b".into())));
+    ecs.add_position(a.id, position(1.0, 2.0, 3.0));
+    assert!(Ecs::replay(&initial, &journal, 3) == ecs);
+}
+
+#[test]
+fn untracked_writes_are_not_recorded() {
+    let mut ecs = Ecs::new();
+    let a = ecs.spawn(FatEntity::new().health(CHealth { hp: 10 }));
+    ecs.start_journal(false);
+    ecs.health_mut_unwrap(a.id).hp = 0;
+    ecs.remove_health(a.id);
+    ecs.remove_health(a.id);
+    assert_eq!(
+        ecs.take_journal().unwrap().records,
+        vec![Mutation::Remove(a, ComponentType::CHealth)]
+    );
+}
+
+#[test]
+fn writes_are_seen_until_the_next_mutation() {
+    let mut ecs = Ecs::new();
+    let a = ecs.spawn(FatEntity::new().health(CHealth { hp: 10 }));
+    ecs.start_journal(true);
+
+    let health = ecs.health_mut_unwrap(a.id);
+    health.hp = 9;
+    ecs.add_name(a.id, CName("a".into()));
+    // the `&mut` outlived the mutation, so this write is missed
+    health.hp = 8;
+    ecs.remove_name(a.id);
+    // a new one is seen again
+    ecs.health_mut_unwrap(a.id).hp = 7;
+    ecs.flush_journal();
+
+    let records = ecs.take_journal().unwrap().records;
+    let health = |hp| Mutation::Insert(a, ComponentTypeContaining::CHealth(CHealth { hp }));
+    assert_eq!(
+        records,
+        [
+            health(9),
+            Mutation::Insert(a, ComponentTypeContaining::CName(CName("a".into()))),
+            Mutation::Remove(a, ComponentType::CName),
+            health(7),
+        ]
+    );
+}
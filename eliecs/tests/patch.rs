@@ -0,0 +1,82 @@
+mod common;
+
+use common::*;
+
+#[test]
+fn apply_fat_overwrites_present_components() {
+    let mut ecs = Ecs::new();
+    let e = ecs.spawn(
+        FatEntity::new()
+            .health(CHealth { hp: 10 })
+            .name(CName("orc".into())),
+    );
+
+    assert!(ecs.apply_fat(
+        e,
+        FatEntity::new()
+            .health(CHealth { hp: 4 })
+            .position(position(1.0, 0.0, 0.0)),
+    ));
+    assert_eq!(
+        ecs.snapshot_entity(e),
+        FatEntity::new()
+            .health(CHealth { hp: 4 })
+            .name(CName("orc".into()))
+            .position(position(1.0, 0.0, 0.0))
+    );
+
+    ecs.despawn(e);
+    assert!(!ecs.apply_fat(e, FatEntity::new().health(CHealth { hp: 1 })));
+    assert_eq!(ecs.query_health().count(), 0);
+}
+
+#[test]
+fn apply_fat_masked_removes_first() {
+    let mut ecs = Ecs::new();
+    let e = ecs.spawn(
+        FatEntity::new()
+            .health(CHealth { hp: 10 })
+            .name(CName("orc".into()))
+            .position(position(0.0, 0.0, 0.0)),
+    );
+
+    let remove = ComponentMask::new()
+        .with(ComponentType::CName)
+        .with(ComponentType::CHealth);
+    assert!(ecs.apply_fat_masked(e, FatEntity::new().health(CHealth { hp: 1 }), remove));
+    assert_eq!(
+        ecs.components_of(e),
+        [ComponentType::CHealth, ComponentType::CPosition]
+    );
+    assert_eq!(ecs.health_unwrap(e.id).hp, 1);
+}
+
+#[test]
+fn component_mask() {
+    let mut mask = ComponentMask::new();
+    assert!(mask.is_empty());
+    mask.insert(ComponentType::CTarget);
+    mask.insert(ComponentType::CHealth);
+    assert!(mask.contains(ComponentType::CTarget));
+    assert!(!mask.contains(ComponentType::CName));
+    assert_eq!(
+        mask.iter().collect::<Vec<_>>(),
+        [ComponentType::CHealth, ComponentType::CTarget]
+    );
+    mask.remove(ComponentType::CTarget);
+    assert_eq!(mask, [ComponentType::CHealth].into_iter().collect());
+}
+
+#[test]
+fn merge_prefers_the_override() {
+    let base = FatEntity::new()
+        .health(CHealth { hp: 10 })
+        .name(CName("orc".into()));
+    let merged = base.merge(FatEntity::new().name(CName("archer".into())));
+    assert_eq!(
+        merged,
+        FatEntity::new()
+            .health(CHealth { hp: 10 })
+            .name(CName("archer".into()))
+    );
+}
@@ -0,0 +1,158 @@
+mod common;
+
+use common::*;
+use eliecs::PrefabError;
+
+fn registry() -> Prefabs {
+    let mut prefabs = Prefabs::new();
+    prefabs.insert(
+        "orc",
+        Prefab::new(
+            FatEntity::new()
+                .health(CHealth { hp: 10 })
+                .name(CName("orc".into()))
+                .position(position(0.0, 0.0, 0.0)),
+        ),
+    );
+    prefabs.insert(
+        "orc_archer",
+        Prefab::inherit("orc", FatEntity::new().name(CName("orc archer".into())))
+            .child("bow")
+            .child("quiver"),
+    );
+    prefabs.insert(
+        "bow",
+        Prefab::new(FatEntity::new().name(CName("bow".into()))).child("string"),
+    );
+    prefabs.insert(
+        "quiver",
+        Prefab::new(FatEntity::new().name(CName("quiver".into()))),
+    );
+    prefabs.insert(
+        "string",
+        Prefab::new(FatEntity::new().name(CName("string".into()))),
+    );
+    prefabs
+}
+
+#[test]
+fn inherited_components_are_overridden() {
+    let prefabs = registry();
+    let archer = prefabs.resolve("orc_archer").unwrap();
+    assert_eq!(archer.name, Some(CName("orc archer".into())));
+    assert_eq!(archer.health, Some(CHealth { hp: 10 }));
+    assert_eq!(archer.position, Some(position(0.0, 0.0, 0.0)));
+}
+
+#[test]
+fn spawn_prefab_spawns_children() {
+    let mut ecs = Ecs::new();
+    *ecs.prefabs_mut() = registry();
+
+    let archer = ecs.spawn_prefab("orc_archer").unwrap();
+    assert_eq!(ecs.name_unwrap(archer.root.id).0, "orc archer");
+    assert_eq!(ecs.health_unwrap(archer.root.id).hp, 10);
+
+    let names = archer
+        .children
+        .iter()
+        .map(|child| ecs.name_unwrap(child.root.id).0.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["bow", "quiver"]);
+    let string = &archer.children[0].children[0];
+    assert_eq!(ecs.name_unwrap(string.root.id).0, "string");
+    assert_eq!(archer.entities().len(), 4);
+    assert!(archer.entities().iter().all(|&e| ecs.is_alive(e)));
+}
+
+#[test]
+fn children_are_inherited() {
+    let mut ecs = Ecs::new();
+    *ecs.prefabs_mut() = registry();
+    ecs.prefabs_mut().insert(
+        "orc_captain",
+        Prefab::inherit("orc_archer", FatEntity::new()).child("banner"),
+    );
+    ecs.prefabs_mut().insert(
+        "banner",
+        Prefab::new(FatEntity::new().name(CName("banner".into()))),
+    );
+
+    let captain = ecs.spawn_prefab("orc_captain").unwrap();
+    let names = captain
+        .children
+        .iter()
+        .map(|child| ecs.name_unwrap(child.root.id).0.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["bow", "quiver", "banner"]);
+    assert_eq!(captain.entities().len(), 5);
+
+    ecs.prefabs_mut().remove("banner");
+    assert_eq!(
+        ecs.spawn_prefab("orc_captain"),
+        Err(PrefabError::Unknown("banner".into()))
+    );
+}
+
+#[test]
+fn snapshots_share_prefabs() {
+    let mut ecs = Ecs::new();
+    *ecs.prefabs_mut() = registry();
+    let snapshot = ecs.snapshot();
+    assert!(snapshot.prefabs().contains("orc"));
+
+    ecs.prefabs_mut().remove("orc");
+    assert!(snapshot.prefabs().contains("orc"));
+    let mut copy = ecs.clone();
+    copy.restore(&snapshot);
+    ecs.restore(&snapshot);
+    assert!(ecs.prefabs().contains("orc"));
+    assert!(copy.prefabs() == ecs.prefabs());
+}
+
+#[test]
+fn registry_loads_from_json() {
+    let prefabs: Prefabs = serde_json::from_str(
+        r#"{
+            "orc": { "template": { "health": { "hp": 10 } } },
+            "orc_archer": { "parent": "orc", "template": { "name": "archer" }, "children": ["bow"] },
+            "bow": { "template": { "name": "bow" } }
+        }"#,
+    )
+    .unwrap();
+
+    let mut ecs = Ecs::new();
+    ecs.prefabs_mut().extend(prefabs);
+    let archer = ecs.spawn_prefab("orc_archer").unwrap();
+    assert_eq!(ecs.name_unwrap(archer.root.id).0, "archer");
+    assert_eq!(ecs.health_unwrap(archer.root.id).hp, 10);
+    assert_eq!(ecs.name_unwrap(archer.children[0].root.id).0, "bow");
+}
+
+#[test]
+fn broken_prefabs_spawn_nothing() {
+    let mut ecs = Ecs::new();
+    *ecs.prefabs_mut() = registry();
+    ecs.prefabs_mut()
+        .insert("goblin", Prefab::inherit("missing", FatEntity::new()));
+    ecs.prefabs_mut().insert(
+        "ouroboros",
+        Prefab::new(FatEntity::new())
+            .child("bow")
+            .child("ouroboros"),
+    );
+
+    assert_eq!(
+        ecs.spawn_prefab("nope"),
+        Err(PrefabError::Unknown("nope".into()))
+    );
+    assert_eq!(
+        ecs.spawn_prefab("goblin"),
+        Err(PrefabError::Unknown("missing".into()))
+    );
+    assert_eq!(
+        ecs.spawn_prefab("ouroboros"),
+        Err(PrefabError::Cycle("ouroboros".into()))
+    );
+    assert!(ecs == Ecs::new());
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use common::*;
+
+fn world() -> (Ecs, Vec<eliecs::Entity>) {
+    let mut ecs = Ecs::new();
+    let entities = vec![
+        ecs.spawn(
+            FatEntity::new()
+                .position(position(0.0, 0.0, 0.0))
+                .name(CName("a".into())),
+        ),
+        ecs.spawn(
+            FatEntity::new()
+                .position(position(1.0, 0.0, 0.0))
+                .name(CName("b".into()))
+                .health(CHealth { hp: 0 }),
+        ),
+        ecs.spawn(FatEntity::new().position(position(2.0, 0.0, 0.0))),
+        ecs.spawn(FatEntity::new().name(CName("d".into()))),
+    ];
+    (ecs, entities)
+}
+
+#[test]
+fn include_and_exclude() {
+    let (ecs, e) = world();
+    let query = DynQuery::new()
+        .with(ComponentType::CPosition)
+        .with(ComponentType::CName);
+    let mut found = ecs.query_dyn(&query).collect::<Vec<_>>();
+    found.sort_by_key(|e| e.id);
+    assert_eq!(found, [e[0], e[1]]);
+
+    let query = query.without(ComponentType::CHealth);
+    assert_eq!(ecs.query_dyn(&query).collect::<Vec<_>>(), [e[0]]);
+}
+
+#[test]
+fn empty_include_goes_through_every_entity() {
+    let (mut ecs, e) = world();
+    ecs.despawn(e[2]);
+    let mut found = ecs
+        .query_dyn(&DynQuery::new().without(ComponentType::CHealth))
+        .collect::<Vec<_>>();
+    found.sort_by_key(|e| e.id);
+    assert_eq!(found, [e[0], e[3]]);
+    assert_eq!(ecs.query_dyn(&DynQuery::new()).count(), 3);
+}
+
+#[test]
+fn fetch_returns_included_components() {
+    let (ecs, e) = world();
+    let query = DynQuery::new()
+        .with(ComponentType::CHealth)
+        .with(ComponentType::CName);
+    assert_eq!(
+        ecs.query_dyn_fetch(&query).collect::<Vec<_>>(),
+        [(
+            e[1],
+            vec![
+                ComponentTypeContaining::CHealth(CHealth { hp: 0 }),
+                ComponentTypeContaining::CName(CName("b".into())),
+            ]
+        )]
+    );
+}
@@ -0,0 +1,186 @@
+mod common;
+
+use common::*;
+use eliecs::reflect::{from_value, to_value, FieldInfo, Reflect, Value};
+
+#[test]
+fn field_metadata() {
+    assert_eq!(
+        CPosition::FIELDS.iter().map(|f| f.name).collect::<Vec<_>>(),
+        ["x", "y", "z"]
+    );
+    assert_eq!(
+        CInventory::FIELDS,
+        [FieldInfo {
+            name: "items",
+            ty: "Vec<(Entity, u32)>",
+            offset: 0,
+        }]
+    );
+    assert_eq!(CParent::FIELDS[0].ty, "Option<Entity>");
+    assert_eq!(
+        CTarget::FIELDS
+            .iter()
+            .find(|f| f.name == "range")
+            .unwrap()
+            .offset,
+        std::mem::offset_of!(CTarget, range)
+    );
+    assert_eq!(ComponentType::CName.fields()[0].name, "0");
+}
+
+#[test]
+fn get_and_set_fields() {
+    let mut p = position(1.0, 2.0, 3.0);
+    assert_eq!(p.field("y"), Some(Ok(Value::Float(2.0))));
+    assert_eq!(p.field("w"), None);
+    p.set_field("y", Value::Int(5)).unwrap();
+    assert_eq!(p.y, 5.0);
+    assert!(p.set_field("y", Value::String("a".into())).is_err());
+    assert!(p.set_field("w", Value::Float(1.0)).is_err());
+    assert_eq!(p.y, 5.0);
+
+    let mut name = CName("orc".into());
+    name.set_field("0", Value::String("elf".into())).unwrap();
+    assert_eq!(name.0, "elf");
+}
+
+#[test]
+fn nested_paths() {
+    let mut ecs = Ecs::new();
+    let a = ecs.spawn(FatEntity::new());
+    let b = ecs.spawn(FatEntity::new());
+    let mut inventory = CInventory {
+        items: vec![(a, 1), (b, 2)],
+    };
+    assert_eq!(inventory.get_path("items.1.0"), Ok(Value::Entity(b)));
+    inventory.set_path("items.1.1", Value::Int(7)).unwrap();
+    assert_eq!(inventory.items[1], (b, 7));
+    assert!(inventory.get_path("items.5").is_err());
+
+    let mut parent = CParent(None);
+    assert_eq!(parent.get_path("0"), Ok(Value::None));
+    parent.set_field("0", Value::Entity(a)).unwrap();
+    assert_eq!(parent.0, Some(a));
+}
+
+#[test]
+fn ecs_fields_are_recorded() {
+    let mut ecs = Ecs::new();
+    let e = ecs.spawn(FatEntity::new().target(CTarget {
+        target: eliecs::Entity::from_bits(1 << 32).unwrap(),
+        range: 1.0,
+    }));
+    assert_eq!(
+        ecs.get_field(e.id, ComponentType::CTarget, "range"),
+        Ok(Value::Float(1.0))
+    );
+
+    ecs.begin_transaction();
+    ecs.set_field(e.id, ComponentType::CTarget, "range", Value::Float(4.0))
+        .unwrap();
+    ecs.commit();
+    assert_eq!(ecs.target_unwrap(e.id).range, 4.0);
+    assert!(ecs
+        .set_field(e.id, ComponentType::CHealth, "hp", Value::Int(1))
+        .is_err());
+
+    ecs.undo();
+    assert_eq!(ecs.target_unwrap(e.id).range, 1.0);
+}
+
+#[test]
+fn values_round_trip() {
+    let target = CTarget {
+        target: eliecs::Entity::from_bits(3 | 2 << 32).unwrap(),
+        range: 1.5,
+    };
+    let value = to_value(&target).unwrap();
+    assert_eq!(
+        value,
+        Value::Struct(vec![
+            ("target".into(), Value::Entity(target.target)),
+            ("range".into(), Value::Float(1.5)),
+        ])
+    );
+    assert_eq!(from_value::<CTarget>(value), Ok(target));
+
+    let c = ComponentTypeContaining::CName(CName("orc".into()));
+    assert_eq!(from_value(to_value(&c).unwrap()), Ok(c));
+    assert_eq!(
+        from_value::<Option<ComponentType>>(Value::String("CHealth".into())),
+        Ok(Some(ComponentType::CHealth))
+    );
+
+    for v in [u64::MAX as i128, i128::MIN] {
+        assert_eq!(to_value(&v), Ok(Value::Int(v)));
+        assert_eq!(from_value::<i128>(Value::Int(v)), Ok(v));
+    }
+    assert_eq!(
+        from_value::<u64>(to_value(&u64::MAX).unwrap()),
+        Ok(u64::MAX)
+    );
+    assert!(to_value(&u128::MAX).is_err());
+    assert!(from_value::<u32>(Value::Int(-1)).is_err());
+}
+
+mod serde_attrs {
+    use eliecs::{
+        components,
+        reflect::{to_value, Reflect, Value},
+    };
+    use serde::{Deserialize, Serialize};
+
+    components! {
+        #[derive(Debug, Serialize, Deserialize, Clone)]
+        #[serde(rename_all = "camelCase")]
+        pub struct CBody {
+            pub max_speed: f32,
+            #[serde(rename = "m")]
+            pub mass: f32,
+            // `Instant` isn't `Serialize`
+            #[serde(skip)]
+            pub started: Option<std::time::Instant>,
+        }
+
+        #[derive(Debug, Serialize, Deserialize, Clone)]
+        pub struct CPair(#[serde(skip)] pub Option<std::time::Instant>, pub u32);
+
+        // serde writes a newtype's field even if it's skipped
+        #[derive(Debug, Serialize, Deserialize, Clone)]
+        pub struct CTicks(#[serde(skip)] pub u32);
+    }
+
+    #[test]
+    fn fields_are_named_like_serde_names_them() {
+        let mut body = CBody {
+            max_speed: 2.0,
+            mass: 3.0,
+            started: Some(std::time::Instant::now()),
+        };
+        let names = CBody::FIELDS.iter().map(|f| f.name).collect::<Vec<_>>();
+        assert_eq!(names, ["maxSpeed", "m"]);
+        assert_eq!(
+            to_value(&body).unwrap(),
+            Value::Struct(vec![
+                ("maxSpeed".into(), Value::Float(2.0)),
+                ("m".into(), Value::Float(3.0)),
+            ])
+        );
+        assert_eq!(body.field("started"), None);
+        body.set_field("m", Value::Float(4.0)).unwrap();
+        assert_eq!(body.mass, 4.0);
+        assert!(body.started.is_some());
+
+        let mut pair = CPair(None, 1);
+        assert_eq!(CPair::FIELDS.len(), 1);
+        assert_eq!(pair.get_path("0"), Ok(Value::Int(1)));
+        pair.set_path("0", Value::Int(5)).unwrap();
+        assert_eq!((pair.0, pair.1), (None, 5));
+        assert_eq!(Ecs::schema().component("CBody").unwrap().fields.len(), 2);
+
+        assert_eq!(CTicks::FIELDS.len(), 1);
+        assert_eq!(CTicks(3).get_path("0"), Ok(Value::Int(3)));
+        assert!(Ecs::schema().validate().is_ok());
+    }
+}
@@ -0,0 +1,249 @@
+mod common;
+
+use std::sync::mpsc;
+
+use common::*;
+use eliecs::{
+    replication::{Client, Interest, Server},
+    Entity,
+};
+
+#[test]
+fn mirrors_replicated_components_over_channel() {
+    let mut world = Ecs::new();
+    let mut server = Server::<Ecs>::new();
+    let client_id = server.connect();
+
+    let (tx, rx) = mpsc::channel::<Vec<u8>>();
+    let mut replica = Ecs::new();
+    // the client already has local-only entities, so ids won't line up with the server's
+    replica.spawn(FatEntity::new().name(CName("local ui".into())));
+    let mut client = Client::new();
+
+    let tick =
+        |world: &Ecs, server: &mut Server<Ecs>, replica: &mut Ecs, client: &mut Client<Ecs>| {
+            for (to, packet) in server.update(world) {
+                assert_eq!(to, client_id);
+                tx.send(packet).unwrap();
+            }
+            for packet in rx.try_iter() {
+                client.receive(replica, &packet).unwrap();
+            }
+        };
+
+    let player = world.spawn(
+        FatEntity::new()
+            .position(position(1.0, 2.0, 3.0))
+            .name(CName("player".into()))
+            .health(CHealth { hp: 50 }),
+    );
+    let turret = world.spawn(FatEntity::new().target(CTarget {
+        target: player,
+        range: 10.0,
+    }));
+    tick(&world, &mut server, &mut replica, &mut client);
+
+    let local_player = client.local(player).unwrap();
+    let local_turret = client.local(turret).unwrap();
+    assert_ne!(local_player, player);
+    assert_eq!(replica.position_unwrap(local_player.id).x, 1.0);
+    assert_eq!(replica.name_unwrap(local_player.id).0, "player");
+    assert!(replica.health(local_player.id).is_none());
+    assert_eq!(replica.target_unwrap(local_turret.id).target, local_player);
+
+    // nothing changed, nothing sent
+    assert!(server.update(&world).is_empty());
+
+    world.position_mut_unwrap(player.id).x = 5.0;
+    world.health_mut_unwrap(player.id).hp = 1;
+    world.remove_name(player.id);
+    tick(&world, &mut server, &mut replica, &mut client);
+    assert_eq!(replica.position_unwrap(local_player.id).x, 5.0);
+    assert!(replica.name(local_player.id).is_none());
+
+    world.despawn(turret);
+    tick(&world, &mut server, &mut replica, &mut client);
+    assert!(!replica.is_alive(local_turret));
+    assert!(client.local(turret).is_none());
+    assert_eq!(replica.name_unwrap(0).0, "local ui");
+}
+
+#[test]
+fn only_entities_with_replicated_components_are_sent() {
+    let mut world = Ecs::new();
+    let mut server = Server::<Ecs>::new();
+    let client_id = server.connect();
+    let mut replica = Ecs::new();
+    let mut client = Client::new();
+
+    // health stays on the server, so there's nothing to send
+    let e = world.spawn(FatEntity::new().health(CHealth { hp: 3 }));
+    assert!(server.update_client(client_id, &world).is_none());
+
+    world.add_name(e.id, CName("e".into()));
+    let packet = server.update_client(client_id, &world).unwrap();
+    client.receive(&mut replica, &packet).unwrap();
+    let local = client.local(e).unwrap();
+    assert_eq!(replica.name_unwrap(local.id).0, "e");
+
+    world.health_mut_unwrap(e.id).hp = 2;
+    assert!(server.update_client(client_id, &world).is_none());
+
+    world.remove_name(e.id);
+    let packet = server.update_client(client_id, &world).unwrap();
+    client.receive(&mut replica, &packet).unwrap();
+    assert!(!replica.is_alive(local));
+    assert!(client.local(e).is_none());
+}
+
+#[test]
+fn interest_spawns_and_despawns_on_scope_changes() {
+    let mut world = Ecs::new();
+    let mut server = Server::<Ecs>::new();
+    let near = server.connect();
+    server.connect();
+    let mut replicas = [(Ecs::new(), Client::new()), (Ecs::new(), Client::new())];
+
+    // `near` sees x < 10, `far` sees x >= 10
+    let mut interest = |client: u32, world: &Ecs, e: Entity| {
+        world
+            .position(e.id)
+            .is_some_and(|p| (p.x < 10.0) == (client == near))
+    };
+    let mut tick = |world: &Ecs, server: &mut Server<Ecs>| {
+        for (client, packet) in server.update_with(world, &mut interest) {
+            let (replica, c) = &mut replicas[client as usize];
+            c.receive(replica, &packet).unwrap();
+        }
+        replicas
+            .iter()
+            .map(|(_, c)| c.entity_map().len())
+            .collect::<Vec<_>>()
+    };
+
+    let mover = world.spawn(FatEntity::new().position(position(0.0, 0.0, 0.0)));
+    world.spawn(FatEntity::new().position(position(20.0, 0.0, 0.0)));
+    assert_eq!(tick(&world, &mut server), vec![1, 1]);
+
+    world.position_mut_unwrap(mover.id).x = 15.0;
+    assert_eq!(tick(&world, &mut server), vec![0, 2]);
+
+    world.position_mut_unwrap(mover.id).x = 5.0;
+    assert_eq!(tick(&world, &mut server), vec![1, 1]);
+}
+
+#[test]
+fn budget_sends_high_priority_first_without_starving() {
+    struct ByHealth;
+    impl Interest<Ecs> for ByHealth {
+        fn relevant(&mut self, _: u32, _: &Ecs, _: Entity) -> bool {
+            true
+        }
+        fn priority(&mut self, _: u32, world: &Ecs, e: Entity) -> f32 {
+            world.health(e.id).map_or(0.0, |h| h.hp as f32)
+        }
+    }
+
+    let mut world = Ecs::new();
+    let entities = (0..20)
+        .map(|i| {
+            world.spawn(
+                FatEntity::new()
+                    .name(CName(format!("entity {i}")))
+                    .health(CHealth { hp: i }),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let mut server = Server::<Ecs>::new();
+    server.set_budget(Some(200));
+    let client_id = server.connect();
+    let mut replica = Ecs::new();
+    let mut client = Client::new();
+
+    let mut packets = 0;
+    while let Some(packet) = server.update_client_with(client_id, &world, &mut ByHealth) {
+        assert!(packet.len() <= 200);
+        client.receive(&mut replica, &packet).unwrap();
+        if packets == 0 {
+            // the healthiest entity is in the first packet, the weakest isn't
+            assert!(client.local(entities[19]).is_some());
+            assert!(client.local(entities[0]).is_none());
+        }
+        packets += 1;
+        assert!(packets < 20);
+    }
+    assert!(packets > 1);
+    for e in entities {
+        let local = client.local(e).unwrap();
+        assert_eq!(replica.name(local.id), world.name(e.id));
+    }
+}
+
+#[test]
+fn references_resolve_once_their_target_is_sent() {
+    struct TurretsFirst;
+    impl Interest<Ecs> for TurretsFirst {
+        fn relevant(&mut self, _: u32, world: &Ecs, e: Entity) -> bool {
+            world.position(e.id).is_some_and(|p| p.x < 10.0)
+        }
+        fn priority(&mut self, _: u32, world: &Ecs, e: Entity) -> f32 {
+            if world.target(e.id).is_some() {
+                1.0
+            } else {
+                0.0
+            }
+        }
+    }
+    let turret = |x: f32, target: Entity| {
+        FatEntity::new()
+            .position(position(x, 0.0, 0.0))
+            .target(CTarget {
+                target,
+                range: 10.0,
+            })
+    };
+
+    let mut world = Ecs::new();
+    let player = world.spawn(
+        FatEntity::new()
+            .position(position(20.0, 0.0, 0.0))
+            .name(CName("player".into())),
+    );
+    let first = world.spawn(turret(0.0, player));
+
+    let mut server = Server::<Ecs>::new();
+    let client_id = server.connect();
+    // the client's own entity has the id the player has on the server
+    let mut replica = Ecs::new();
+    let bystander = replica.spawn(FatEntity::new().name(CName("local".into())));
+    assert_eq!(bystander, player);
+    let mut client = Client::new();
+    let target = |client: &Client<Ecs>, replica: &Ecs, turret: Entity| {
+        let local = client.local(turret).unwrap();
+        replica.target_unwrap(local.id).target
+    };
+
+    // the player is out of interest
+    let packet = server.update_client_with(client_id, &world, &mut TurretsFirst);
+    client.receive(&mut replica, &packet.unwrap()).unwrap();
+    assert!(client.local(player).is_none());
+    assert!(!replica.is_alive(target(&client, &replica, first)));
+
+    // with one entity per packet, a turret spawned when the player comes into interest goes
+    // before the player
+    server.set_budget(Some(1));
+    world.position_mut_unwrap(player.id).x = 5.0;
+    let second = world.spawn(turret(1.0, player));
+    let packet = server.update_client_with(client_id, &world, &mut TurretsFirst);
+    client.receive(&mut replica, &packet.unwrap()).unwrap();
+    assert!(client.local(player).is_none());
+    assert!(!replica.is_alive(target(&client, &replica, second)));
+
+    while let Some(packet) = server.update_client_with(client_id, &world, &mut TurretsFirst) {
+        client.receive(&mut replica, &packet).unwrap();
+    }
+    let local_player = client.local(player).unwrap();
+    assert_eq!(target(&client, &replica, first), local_player);
+    assert_eq!(target(&client, &replica, second), local_player);
+}
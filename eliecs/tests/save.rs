@@ -0,0 +1,128 @@
+mod common;
+
+use common::*;
+use eliecs::{reflect::Value, save::SaveFile};
+
+fn world() -> Ecs {
+    let mut ecs = Ecs::new();
+    let a = ecs.spawn(
+        FatEntity::new()
+            .position(position(1.0, 2.0, 3.0))
+            .name(CName("a".into()))
+            .net_id(CNetId(u64::MAX)),
+    );
+    let b = ecs.spawn(
+        FatEntity::new()
+            .health(CHealth { hp: -3 })
+            .parent(CParent(Some(a)))
+            .target(CTarget {
+                target: a,
+                range: 0.5,
+            }),
+    );
+    let gone = ecs.spawn(FatEntity::new());
+    ecs.spawn(FatEntity::new().inventory(CInventory {
+        items: vec![(b, 2), (gone, 1)],
+    }));
+    ecs.despawn(gone);
+    ecs
+}
+
+#[test]
+fn bincode_round_trips_unchanged() {
+    let ecs = world();
+    let schema = Ecs::schema();
+    let bytes = bincode::serialize(&ecs).unwrap();
+    let save = SaveFile::from_bincode(&schema, &bytes).unwrap();
+    assert_eq!(save.to_bincode(&schema).unwrap(), bytes);
+}
+
+#[test]
+fn converts_between_bincode_and_json() {
+    let ecs = world();
+    let schema = Ecs::schema();
+    let save = SaveFile::from_bincode(&schema, &bincode::serialize(&ecs).unwrap()).unwrap();
+
+    let json = serde_json::to_string(&save.with_schema(&schema)).unwrap();
+    assert_eq!(json, serde_json::to_string(&ecs).unwrap());
+
+    let from_json =
+        SaveFile::deserialize(&schema, &mut serde_json::Deserializer::from_str(&json)).unwrap();
+    assert_eq!(from_json, save);
+    let back: Ecs = bincode::deserialize(&from_json.to_bincode(&schema).unwrap()).unwrap();
+    assert!(back == ecs);
+}
+
+#[test]
+fn reads_components() {
+    let ecs = world();
+    let schema = Ecs::schema();
+    let save = SaveFile::from_bincode(&schema, &bincode::serialize(&ecs).unwrap()).unwrap();
+    let a = save.entities().next().unwrap();
+    let components = save
+        .components_of(&schema, a)
+        .into_iter()
+        .map(|(c, v)| (c.snake_name.as_str(), v.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        components,
+        [
+            ("name", Value::String("a".into())),
+            ("net_id", Value::Int(u64::MAX.into())),
+            (
+                "position",
+                Value::Struct(vec![
+                    ("x".into(), Value::Float(1.0)),
+                    ("y".into(), Value::Float(2.0)),
+                    ("z".into(), Value::Float(3.0)),
+                ])
+            ),
+        ]
+    );
+}
+
+#[test]
+fn finds_problems() {
+    let ecs = world();
+    let schema = Ecs::schema();
+    let mut save = SaveFile::from_bincode(&schema, &bincode::serialize(&ecs).unwrap()).unwrap();
+    assert!(save.problems(&schema).is_empty());
+
+    let dangling = save.dangling_references(&schema);
+    assert_eq!(dangling.len(), 1);
+    assert_eq!(dangling[0].1, "CInventory");
+
+    let alive = save.entities().next().unwrap();
+    save.free_list.push(alive);
+    save.existence.remove(alive.id);
+    save.existence.insert(alive.id, alive.version);
+    save.existence.remove(1);
+    assert_eq!(
+        save.problems(&schema),
+        [
+            "CHealth on 1, which isn't alive",
+            "CParent on 1, which isn't alive",
+            "CTarget on 1, which isn't alive",
+            &format!("{alive:?} is free, but 0 is alive"),
+        ]
+    );
+}
+
+#[test]
+fn rejects_schemas_whose_fields_dont_fit() {
+    let ecs = world();
+    let bytes = bincode::serialize(&ecs).unwrap();
+    let mut schema = Ecs::schema();
+    let name = schema
+        .components
+        .iter_mut()
+        .find(|c| c.name == "CName")
+        .unwrap();
+    name.fields.clear();
+    assert_eq!(
+        schema.validate(),
+        Err("CName is a newtype struct with 0 fields".to_owned())
+    );
+    let err = SaveFile::from_bincode(&schema, &bytes).unwrap_err();
+    assert!(err.to_string().contains("CName is a newtype struct"));
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use common::*;
+
+#[test]
+fn scene_round_trip() {
+    let mut ecs = Ecs::new();
+    let a = ecs.spawn(
+        FatEntity::new()
+            .position(position(1.0, 2.0, 3.0))
+            .name(CName("a".into())),
+    );
+    let dead = ecs.spawn(FatEntity::new().name(CName("dead".into())));
+    let b = ecs.spawn(FatEntity::new().health(CHealth { hp: 10 }));
+    ecs.despawn(dead);
+
+    let json = serde_json::to_string(&ecs.to_scene()).unwrap();
+    let scene: Scene = serde_json::from_str(&json).unwrap();
+    assert_eq!(scene.len(), 2);
+
+    let mut loaded = Ecs::new();
+    loaded.spawn(FatEntity::new().name(CName("already here".into())));
+    let map = loaded.load_scene(scene);
+
+    let new_a = map.get(a).unwrap();
+    let new_b = map.get(b).unwrap();
+    assert_ne!(new_a, a);
+    assert!(loaded.is_alive(new_a) && loaded.is_alive(new_b));
+    assert_eq!(loaded.name_unwrap(new_a.id).0, "a");
+    assert_eq!(loaded.position_unwrap(new_a.id).z, 3.0);
+    assert_eq!(loaded.health_unwrap(new_b.id).hp, 10);
+    assert!(loaded.position(new_b.id).is_none());
+}
+
+#[test]
+fn scene_json_is_tagged_per_entity() {
+    let mut ecs = Ecs::new();
+    ecs.spawn(FatEntity::new().name(CName("x".into())));
+
+    let value = serde_json::to_value(ecs.to_scene()).unwrap();
+    assert_eq!(
+        value,
+        serde_json::json!({
+            "entities": [{
+                "entity": [0, 1],
+                "components": [{ "type": "CName", "value": "x" }],
+            }]
+        })
+    );
+}
+
+#[test]
+fn load_scene_remaps_entity_references() {
+    let mut ecs = Ecs::new();
+    let sword = ecs.spawn(FatEntity::new().name(CName("sword".into())));
+    let player = ecs.spawn(
+        FatEntity::new()
+            .parent(CParent(None))
+            .inventory(CInventory {
+                items: vec![(sword, 1)],
+            }),
+    );
+    let enemy = ecs.spawn(FatEntity::new().target(CTarget {
+        target: player,
+        range: 5.0,
+    }));
+    ecs.add_parent(sword.id, CParent(Some(player)));
+    let scene = ecs.to_scene();
+
+    let mut loaded = Ecs::new();
+    for _ in 0..3 {
+        loaded.spawn(FatEntity::new());
+    }
+    let map = loaded.load_scene(scene);
+    let (sword, player, enemy) = (
+        map.get(sword).unwrap(),
+        map.get(player).unwrap(),
+        map.get(enemy).unwrap(),
+    );
+
+    assert_eq!(loaded.target_unwrap(enemy.id).target, player);
+    assert_eq!(loaded.parent_unwrap(sword.id).0, Some(player));
+    assert_eq!(loaded.parent_unwrap(player.id).0, None);
+    assert_eq!(loaded.inventory_unwrap(player.id).items, vec![(sword, 1)]);
+}
@@ -0,0 +1,75 @@
+mod common;
+
+use common::*;
+use eliecs::schema::{stable_id, Repr, Schema, TypeSchema};
+
+#[test]
+fn describes_every_component() {
+    let schema = Ecs::schema();
+    assert_eq!(schema.format, Schema::FORMAT);
+    assert_eq!(
+        schema
+            .components
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect::<Vec<_>>(),
+        ComponentType::ALL
+            .iter()
+            .map(|ty| format!("C{}", ty.name()))
+            .collect::<Vec<_>>()
+    );
+
+    let target = schema.component("target").unwrap();
+    assert_eq!(target.name, "CTarget");
+    assert_eq!(target.index, ComponentType::CTarget as u32);
+    assert_eq!(target.id, stable_id("CTarget"));
+    assert_eq!(target.repr, Repr::Struct);
+    assert!(target.replicated);
+    assert_eq!(target.fields[0].name, "target");
+    assert_eq!(target.fields[0].shape, TypeSchema::Entity);
+    assert_eq!(target.fields[1].shape, TypeSchema::F32);
+
+    let name = schema.component("CName").unwrap();
+    assert_eq!(name.repr, Repr::Newtype);
+    assert_eq!(name.fields[0].shape, TypeSchema::String);
+    assert!(!schema.component("net_id").unwrap().cloned);
+}
+
+#[test]
+fn round_trips_through_json() {
+    let schema = Ecs::schema();
+    let json = serde_json::to_string_pretty(&schema).unwrap();
+    assert_eq!(serde_json::from_str::<Schema>(&json).unwrap(), schema);
+}
+
+#[test]
+fn stable_ids_only_depend_on_the_name() {
+    assert_eq!(stable_id("CPosition"), stable_id("CPosition"));
+    assert_ne!(stable_id("CPosition"), stable_id("CName"));
+    assert_eq!(stable_id("CPosition"), 0x52bf3bc23aa9cc6d);
+}
+
+#[test]
+fn type_shapes() {
+    use TypeSchema::*;
+    assert_eq!(
+        TypeSchema::parse("Vec<(Entity, u32)>"),
+        Seq(Box::new(Tuple(vec![Entity, U32])))
+    );
+    assert_eq!(
+        TypeSchema::parse("std::collections::HashMap<String, Box<[f32; 3]>>"),
+        Map(Box::new(String), Box::new(Array(Box::new(F32), 3)))
+    );
+    assert_eq!(
+        TypeSchema::parse("Option<&'static str>"),
+        Option(Box::new(String))
+    );
+    assert_eq!(TypeSchema::parse("(u8,)"), Tuple(vec![U8]));
+    assert_eq!(TypeSchema::parse("()"), Unit);
+    assert_eq!(TypeSchema::parse("Stats"), Named("Stats".into()));
+    assert!(!TypeSchema::parse("Vec<Stats>").is_known());
+    assert_eq!(
+        TypeSchema::parse("Vec<(Entity, u32)>").to_string(),
+        "Vec<(Entity, u32)>"
+    );
+}
@@ -0,0 +1,25 @@
+mod common;
+
+use common::*;
+
+#[test]
+fn bincode_round_trips_every_component() {
+    let mut ecs = Ecs::new();
+    let a = ecs.spawn(
+        FatEntity::new()
+            .position(position(1.0, 2.0, 3.0))
+            .name(CName("a".into())),
+    );
+    let b = ecs.spawn(
+        FatEntity::new()
+            .health(CHealth { hp: 3 })
+            .parent(CParent(Some(a))),
+    );
+    let bytes = bincode::serialize(&ecs).unwrap();
+    let back: Ecs = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(back.position(a.id), Some(&position(1.0, 2.0, 3.0)));
+    assert_eq!(back.name(a.id), Some(&CName("a".into())));
+    assert_eq!(back.health(b.id), Some(&CHealth { hp: 3 }));
+    assert_eq!(back.parent(b.id), Some(&CParent(Some(a))));
+    assert_eq!(bincode::serialize(&back).unwrap(), bytes);
+}
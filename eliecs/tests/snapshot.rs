@@ -0,0 +1,46 @@
+mod common;
+
+use common::*;
+use eliecs::SnapshotRing;
+
+#[test]
+fn restore_undoes_everything_after_snapshot() {
+    let mut ecs = Ecs::new();
+    let a = ecs.spawn(FatEntity::new().health(CHealth { hp: 10 }));
+    let snapshot = ecs.snapshot();
+
+    ecs.health_mut_unwrap(a.id).hp = 3;
+    ecs.add_name(a.id, CName("a".into()));
+    let b = ecs.spawn(FatEntity::new().health(CHealth { hp: 1 }));
+    ecs.despawn(a);
+
+    ecs.restore(&snapshot);
+    assert!(ecs.is_alive(a));
+    assert!(!ecs.is_alive(b));
+    assert_eq!(ecs.health_unwrap(a.id).hp, 10);
+    assert!(ecs.name(a.id).is_none());
+    assert_eq!(ecs.query_health().count(), 1);
+
+    // the allocator state came back too, so respawning hands out the same entity
+    assert_eq!(ecs.spawn(FatEntity::new()), b);
+}
+
+#[test]
+fn ring_keeps_last_n_frames() {
+    let mut ecs = Ecs::new();
+    let e = ecs.spawn(FatEntity::new().health(CHealth { hp: 0 }));
+    let mut ring = SnapshotRing::new(4);
+
+    for frame in 0..10 {
+        ecs.health_mut_unwrap(e.id).hp = frame as i32;
+        ring.save(frame, &ecs);
+    }
+    assert_eq!(ring.latest_frame(), Some(9));
+    assert!(!ring.contains(5));
+    assert!(ring.contains(6));
+
+    assert!(ring.restore(7, &mut ecs));
+    assert_eq!(ecs.health_unwrap(e.id).hp, 7);
+    assert!(!ring.restore(2, &mut ecs));
+    assert_eq!(ecs.health_unwrap(e.id).hp, 7);
+}
@@ -0,0 +1,90 @@
+mod common;
+
+use common::*;
+use eliecs::Pool;
+
+#[test]
+fn pool_stats_and_shrinking() {
+    let mut pool = Pool::new();
+    for i in 0..100 {
+        pool.insert(i, i as u64);
+    }
+    for i in 10..100 {
+        pool.remove(i);
+    }
+    let stats = pool.memory_stats();
+    assert_eq!(stats.sparse_len, 100);
+    assert_eq!(stats.dense_len, 10);
+    assert!(stats.dense_capacity >= 100);
+    assert_eq!(stats.occupancy(), 0.1);
+
+    pool.shrink_to_fit();
+    let stats = pool.memory_stats();
+    assert_eq!((stats.sparse_len, stats.sparse_capacity), (10, 10));
+    assert_eq!((stats.dense_len, stats.dense_capacity), (10, 10));
+    assert_eq!(stats.bytes, 10 * 4 + 10 * 16);
+    assert_eq!(stats.occupancy(), 1.0);
+    assert!((0..10).all(|i| pool.get(i) == Some(&(i as u64))));
+    assert!(!pool.contains(50));
+
+    pool.clear();
+    pool.shrink_to_fit();
+    assert_eq!(pool.memory_stats().bytes, 0);
+}
+
+#[test]
+fn ecs_stats() {
+    let mut ecs = Ecs::new();
+    let entities = (0..50)
+        .map(|i| ecs.spawn(FatEntity::new().health(CHealth { hp: i })))
+        .collect::<Vec<_>>();
+    ecs.add_name(entities[0].id, CName("first".into()));
+    for &e in &entities[1..] {
+        ecs.despawn(e);
+    }
+
+    let stats = ecs.stats();
+    assert_eq!(stats.alive, 1);
+    assert_eq!(stats.free, 49);
+    assert_eq!(stats.version_wraparounds, 0);
+    assert_eq!(stats.existence.sparse_len, 50);
+    assert_eq!(stats.components.len(), ComponentType::ALL.len());
+    let (ty, health) = stats.components[ComponentType::CHealth as usize];
+    assert_eq!(ty, ComponentType::CHealth);
+    assert_eq!(health.dense_len, 1);
+    assert_eq!(
+        stats.total().bytes,
+        stats.existence.bytes + stats.components.iter().map(|(_, s)| s.bytes).sum::<usize>()
+    );
+
+    ecs.shrink_to_fit();
+    let shrunk = ecs.stats();
+    assert!(shrunk.total().bytes < stats.total().bytes);
+    assert_eq!(
+        shrunk.components[ComponentType::CHealth as usize]
+            .1
+            .dense_capacity,
+        1
+    );
+    assert_eq!(ecs.health(entities[0].id), Some(&CHealth { hp: 0 }));
+    assert_eq!(ecs.name(entities[0].id), Some(&CName("first".into())));
+    // despawned ids are still reused
+    assert_eq!(ecs.spawn(FatEntity::new()).id, entities[49].id);
+}
+
+#[test]
+fn counts_version_wraparounds() {
+    let mut ecs = Ecs::new();
+    let e = ecs.spawn(FatEntity::new());
+    ecs.despawn(e);
+    let mut json = serde_json::to_value(&ecs).unwrap();
+    json[1] = serde_json::json!([[0, u32::MAX]]);
+    let mut ecs: Ecs = serde_json::from_value(json).unwrap();
+
+    let e = ecs.spawn(FatEntity::new());
+    assert_eq!(e.version.get(), u32::MAX);
+    ecs.despawn(e);
+    assert_eq!(ecs.stats().version_wraparounds, 1);
+    assert_eq!(ecs.spawn(FatEntity::new()).version.get(), 1);
+    assert_eq!(ecs.clone().stats().version_wraparounds, 1);
+}
@@ -0,0 +1,125 @@
+mod common;
+
+use common::*;
+use eliecs::{reflect::Value, QueryError};
+
+fn world() -> (Ecs, Vec<eliecs::Entity>) {
+    let mut ecs = Ecs::new();
+    let boss = ecs.spawn(
+        FatEntity::new()
+            .position(position(0.0, 0.0, 0.0))
+            .name(CName("boss".into()))
+            .health(CHealth { hp: 100 }),
+    );
+    let orc = ecs.spawn(
+        FatEntity::new()
+            .position(position(1.0, 0.0, 0.0))
+            .name(CName("orc".into()))
+            .health(CHealth { hp: 5 })
+            .target(CTarget {
+                target: boss,
+                range: 2.5,
+            }),
+    );
+    let rock = ecs.spawn(
+        FatEntity::new()
+            .position(position(2.0, 0.0, 0.0))
+            .name(CName("rock".into()))
+            .inventory(CInventory {
+                items: vec![(boss, 3)],
+            }),
+    );
+    (ecs, vec![boss, orc, rock])
+}
+
+fn sorted(mut v: Vec<eliecs::Entity>) -> Vec<eliecs::Entity> {
+    v.sort_by_key(|e| e.id);
+    v
+}
+
+#[test]
+fn components_and_conditions() {
+    let (ecs, e) = world();
+    assert_eq!(
+        sorted(ecs.query_text("Position & Name").unwrap()),
+        [e[0], e[1], e[2]]
+    );
+    assert_eq!(ecs.query_text("Position & !Health").unwrap(), [e[2]]);
+    assert_eq!(
+        ecs.query_text("Position & Name where Health.hp < 10")
+            .unwrap(),
+        [e[1]]
+    );
+    assert_eq!(
+        sorted(
+            ecs.query_text("where Health.hp >= 5 && Name != \"boss\"")
+                .unwrap()
+        ),
+        [e[1]]
+    );
+    assert_eq!(ecs.query_text("where Position.x == 1").unwrap(), [e[1]]);
+    assert_eq!(ecs.query_text("where Target.range > 2.0").unwrap(), [e[1]]);
+    assert_eq!(
+        ecs.query_text(&format!("where Target.target == {:?}", e[0]))
+            .unwrap(),
+        [e[1]]
+    );
+    assert_eq!(
+        ecs.query_text("where Inventory.items.0.1 == 3").unwrap(),
+        [e[2]]
+    );
+    // different spellings of the same component
+    assert_eq!(
+        sorted(ecs.query_text("health and CName and not target").unwrap()),
+        [e[0]]
+    );
+}
+
+#[test]
+fn parse_errors_have_spans() {
+    let (ecs, _) = world();
+    let src = "Position & Nme";
+    let error = ecs.query_text(src).unwrap_err();
+    assert_eq!(
+        error,
+        QueryError {
+            message: "unknown component `Nme`".into(),
+            span: 11..14,
+        }
+    );
+    assert_eq!(
+        error.render(src),
+        "Position & Nme\n           ^^^ unknown component `Nme`"
+    );
+
+    assert_eq!(
+        ecs.query_text("Position where Health.hp <").unwrap_err(),
+        QueryError {
+            message: "expected a value".into(),
+            span: 26..26,
+        }
+    );
+    assert_eq!(ecs.query_text("Position Name").unwrap_err().span, 9..13);
+    assert_eq!(ecs.query_text("Name == \"a").unwrap_err().span, 8..10);
+}
+
+#[test]
+fn check_errors_point_at_the_condition() {
+    let (ecs, _) = world();
+    let error = ecs.query_text("Name where Health.mana > 1").unwrap_err();
+    assert_eq!(error.message, "no field `mana`");
+    assert_eq!(error.span, 11..26);
+
+    let error = ecs.query_text("where Name < true").unwrap_err();
+    assert_eq!(error.message, "can't compare string < bool");
+}
+
+#[test]
+fn parsed_queries() {
+    let query =
+        TextQuery::parse("!Health where Position.y <= -1.5", ComponentType::from_name).unwrap();
+    assert_eq!(query.query.include(), [ComponentType::CPosition]);
+    assert_eq!(query.query.exclude(), [ComponentType::CHealth]);
+    assert_eq!(query.conditions[0].fields, ["y"]);
+    assert_eq!(query.conditions[0].value, Value::Float(-1.5));
+}
@@ -0,0 +1,95 @@
+mod common;
+
+use common::*;
+
+fn transfer(ecs: &mut Ecs, from: u32, to: u32, hp: i32) -> Result<(), &'static str> {
+    ecs.health_mut(to).ok_or("no target")?.hp += hp;
+    let from = ecs.health_mut(from).ok_or("no source")?;
+    if from.hp < hp {
+        return Err("not enough");
+    }
+    from.hp -= hp;
+    Ok(())
+}
+
+#[test]
+fn commits_on_ok() {
+    let mut ecs = Ecs::new();
+    let a = ecs.spawn(FatEntity::new().health(CHealth { hp: 10 }));
+    let b = ecs.spawn(FatEntity::new().health(CHealth { hp: 0 }));
+
+    ecs.transaction(|tx| transfer(tx, a.id, b.id, 4)).unwrap();
+    assert_eq!(ecs.health_unwrap(a.id).hp, 6);
+    assert_eq!(ecs.health_unwrap(b.id).hp, 4);
+    // gameplay transactions don't pile up in the undo history
+    assert!(!ecs.history().can_undo());
+}
+
+#[test]
+fn rolls_back_on_err() {
+    let mut ecs = Ecs::new();
+    let a = ecs.spawn(FatEntity::new().health(CHealth { hp: 10 }));
+    let b = ecs.spawn(FatEntity::new().health(CHealth { hp: 0 }));
+    let gone = ecs.spawn(FatEntity::new().name(CName("gone".into())));
+    // the spawns below reuse this id, rolling back must free it with the same version
+    let dead = ecs.spawn(FatEntity::new());
+    ecs.despawn(dead);
+    let before = ecs.snapshot();
+
+    let result = ecs.transaction(|tx| {
+        tx.despawn(gone);
+        tx.spawn(FatEntity::new().name(CName("new".into())));
+        tx.spawn(FatEntity::new());
+        tx.add_name(a.id, CName("a".into()));
+        tx.remove_health(b.id);
+        tx.add_health(b.id, CHealth { hp: 1 });
+        transfer(tx, a.id, b.id, 40)
+    });
+    assert_eq!(result, Err("not enough"));
+    assert!(ecs.is_alive(gone));
+    assert_eq!(ecs.health_unwrap(b.id).hp, 0);
+    assert!(ecs.name(a.id).is_none());
+    assert_eq!(ecs.to_scene().entities.len(), 3);
+    assert!(ecs == before);
+    assert_eq!(ecs.state_hash().unwrap(), before.state_hash().unwrap());
+}
+
+#[test]
+fn rolls_back_on_panic() {
+    let mut ecs = Ecs::new();
+    let a = ecs.spawn(FatEntity::new().health(CHealth { hp: 10 }));
+
+    let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        ecs.transaction(|tx| -> Result<(), ()> {
+            tx.health_mut_unwrap(a.id).hp = 0;
+            panic!("oops");
+        })
+    }));
+    assert!(panicked.is_err());
+    assert_eq!(ecs.health_unwrap(a.id).hp, 10);
+    assert!(!ecs.in_transaction());
+}
+
+#[test]
+fn nested_in_editor_transaction() {
+    let mut ecs = Ecs::new();
+    let a = ecs.spawn(FatEntity::new().health(CHealth { hp: 10 }));
+
+    ecs.begin_transaction();
+    ecs.add_name(a.id, CName("a".into()));
+    let _ = ecs.transaction(|tx| {
+        tx.health_mut_unwrap(a.id).hp = 1;
+        Err::<(), _>(())
+    });
+    ecs.transaction(|tx| {
+        tx.health_mut_unwrap(a.id).hp = 2;
+        Ok::<_, ()>(())
+    })
+    .unwrap();
+    assert!(ecs.commit());
+    assert_eq!(ecs.health_unwrap(a.id).hp, 2);
+
+    ecs.undo();
+    assert_eq!(ecs.health_unwrap(a.id).hp, 10);
+    assert!(ecs.name(a.id).is_none());
+}
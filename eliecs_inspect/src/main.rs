@@ -0,0 +1,146 @@
+//! Looks into saves of any game using eliecs, given the schema it exported with `Ecs::schema()`.
+//! Saves ending in `.json` are read and written as JSON, anything else as bincode.
+
+use std::{path::Path, process::ExitCode};
+
+use eliecs::{save::SaveFile, schema::Schema};
+
+const USAGE: &str = "\
+usage: eliecs_inspect <command> <schema.json> <save> [options]
+
+commands:
+    counts <schema> <save>                  number of entities and of each component
+    dump <schema> <save> [--limit <n>]      entities and their components as JSON
+    convert <schema> <save> <out>           rewrite the save as JSON or bincode, by extension
+    validate <schema> <save>                check the save can be loaded, exit with 1 if not";
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match run(&args) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run(args: &[String]) -> Result<ExitCode, String> {
+    let [command, schema, save, rest @ ..] = args else {
+        return Err(format!("not enough arguments\n\n{USAGE}"));
+    };
+    let schema = read_schema(Path::new(schema))?;
+    let path = Path::new(save);
+    let save = read_save(&schema, path)?;
+
+    match (command.as_str(), rest) {
+        ("counts", []) => counts(&schema, &save),
+        ("dump", []) => dump(&schema, &save, usize::MAX)?,
+        ("dump", [flag, limit]) if flag == "--limit" => {
+            let limit = limit
+                .parse()
+                .map_err(|_| format!("`{limit}` isn't a number"))?;
+            dump(&schema, &save, limit)?
+        }
+        ("convert", [out]) => write_save(&schema, &save, Path::new(out))?,
+        ("validate", []) => return Ok(validate(&schema, &save)),
+        _ => return Err(format!("unknown command or options\n\n{USAGE}")),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "json")
+}
+
+fn read_schema(path: &Path) -> Result<Schema, String> {
+    let json =
+        std::fs::read_to_string(path).map_err(|e| format!("can't read {}: {e}", path.display()))?;
+    let schema: Schema = serde_json::from_str(&json)
+        .map_err(|e| format!("{} isn't a schema: {e}", path.display()))?;
+    if schema.format != Schema::FORMAT {
+        return Err(format!(
+            "{} is in schema format {}, but only {} is supported",
+            path.display(),
+            schema.format,
+            Schema::FORMAT
+        ));
+    }
+    schema
+        .validate()
+        .map_err(|e| format!("{} isn't a valid schema: {e}", path.display()))?;
+    Ok(schema)
+}
+
+fn read_save(schema: &Schema, path: &Path) -> Result<SaveFile, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("can't read {}: {e}", path.display()))?;
+    if is_json(path) {
+        let mut de = serde_json::Deserializer::from_slice(&bytes);
+        SaveFile::deserialize(schema, &mut de).map_err(|e| e.to_string())
+    } else {
+        SaveFile::from_bincode(schema, &bytes).map_err(|e| e.to_string())
+    }
+    .map_err(|e| format!("can't read {}: {e}", path.display()))
+}
+
+fn write_save(schema: &Schema, save: &SaveFile, path: &Path) -> Result<(), String> {
+    let bytes = if is_json(path) {
+        serde_json::to_vec(&save.with_schema(schema)).map_err(|e| e.to_string())
+    } else {
+        save.to_bincode(schema).map_err(|e| e.to_string())
+    }
+    .map_err(|e| format!("can't convert the save: {e}"))?;
+    std::fs::write(path, bytes).map_err(|e| format!("can't write {}: {e}", path.display()))
+}
+
+fn counts(schema: &Schema, save: &SaveFile) {
+    let width = schema
+        .components
+        .iter()
+        .map(|c| c.name.len())
+        .chain(["entities".len(), "free".len()])
+        .max()
+        .unwrap_or(0);
+    println!("{:width$}  {}", "entities", save.existence.len());
+    println!("{:width$}  {}", "free", save.free_list.len());
+    for (c, pool) in schema.components.iter().zip(&save.pools) {
+        println!("{:width$}  {}", c.name, pool.len());
+    }
+}
+
+fn dump(schema: &Schema, save: &SaveFile, limit: usize) -> Result<(), String> {
+    let mut entities = save.entities().collect::<Vec<_>>();
+    entities.sort_by_key(|e| e.id);
+    let entities = entities
+        .into_iter()
+        .take(limit)
+        .map(|e| {
+            let mut object = serde_json::Map::new();
+            object.insert("entity".into(), format!("{e:?}").into());
+            for (c, value) in save.components_of(schema, e) {
+                let value = serde_json::to_value(value).map_err(|e| e.to_string())?;
+                object.insert(c.snake_name.clone(), value);
+            }
+            Ok(serde_json::Value::Object(object))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let json = serde_json::to_string_pretty(&entities).map_err(|e| e.to_string())?;
+    println!("{json}");
+    Ok(())
+}
+
+fn validate(schema: &Schema, save: &SaveFile) -> ExitCode {
+    for (e, component, target) in save.dangling_references(schema) {
+        println!("warning: {component} of {e:?} refers to {target:?}, which isn't alive");
+    }
+    let problems = save.problems(schema);
+    for problem in &problems {
+        println!("error: {problem}");
+    }
+    if problems.is_empty() {
+        println!("ok");
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
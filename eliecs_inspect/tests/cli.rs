@@ -0,0 +1,142 @@
+use std::{path::PathBuf, process::Command};
+
+use eliecs::components;
+use serde::{Deserialize, Serialize};
+
+components! {
+    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+    pub struct CPosition {
+        pub x: f32,
+        pub y: f32,
+    }
+    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+    pub struct CName(pub String);
+
+    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+    pub struct CTarget(pub Option<Entity>);
+}
+
+/// A directory with `schema.json` and `save.bin`, holding two entities and one despawned one.
+fn fixture(name: &str) -> (PathBuf, Ecs) {
+    let dir = std::env::temp_dir().join(format!("eliecs_inspect_{name}_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let mut ecs = Ecs::new();
+    let a = ecs.spawn(
+        FatEntity::new()
+            .position(CPosition { x: 1.0, y: 2.0 })
+            .name(CName("a".into())),
+    );
+    let gone = ecs.spawn(FatEntity::new());
+    ecs.spawn(FatEntity::new().target(CTarget(Some(a))));
+    ecs.spawn(FatEntity::new().target(CTarget(Some(gone))));
+    ecs.despawn(gone);
+
+    std::fs::write(
+        dir.join("schema.json"),
+        serde_json::to_string(&Ecs::schema()).unwrap(),
+    )
+    .unwrap();
+    std::fs::write(dir.join("save.bin"), bincode::serialize(&ecs).unwrap()).unwrap();
+    (dir, ecs)
+}
+
+/// Runs the CLI, returning whether it succeeded and what it printed, stderr after stdout.
+fn inspect(dir: &PathBuf, args: &[&str]) -> (bool, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_eliecs_inspect"))
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .unwrap();
+    (
+        output.status.success(),
+        String::from_utf8([output.stdout, output.stderr].concat()).unwrap(),
+    )
+}
+
+#[test]
+fn counts() {
+    let (dir, _) = fixture("counts");
+    let (ok, out) = inspect(&dir, &["counts", "schema.json", "save.bin"]);
+    assert!(ok);
+    assert_eq!(
+        out,
+        "entities   3\nfree       1\nCName      1\nCPosition  1\nCTarget    2\n"
+    );
+}
+
+#[test]
+fn dump() {
+    let (dir, _) = fixture("dump");
+    let (ok, out) = inspect(&dir, &["dump", "schema.json", "save.bin", "--limit", "1"]);
+    assert!(ok);
+    let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!([{
+            "entity": "0v1",
+            "name": "a",
+            "position": { "x": 1.0, "y": 2.0 },
+        }])
+    );
+}
+
+#[test]
+fn convert_round_trips() {
+    let (dir, ecs) = fixture("convert");
+    assert!(inspect(&dir, &["convert", "schema.json", "save.bin", "save.json"]).0);
+    let json = std::fs::read_to_string(dir.join("save.json")).unwrap();
+    assert!(serde_json::from_str::<Ecs>(&json).unwrap() == ecs);
+
+    assert!(inspect(&dir, &["convert", "schema.json", "save.json", "back.bin"]).0);
+    assert_eq!(
+        std::fs::read(dir.join("back.bin")).unwrap(),
+        std::fs::read(dir.join("save.bin")).unwrap()
+    );
+}
+
+#[test]
+fn validate() {
+    let (dir, mut ecs) = fixture("validate");
+    let (ok, out) = inspect(&dir, &["validate", "schema.json", "save.bin"]);
+    assert!(ok);
+    assert_eq!(
+        out,
+        "warning: CTarget of 3v1 refers to 1v1, which isn't alive\nok\n"
+    );
+
+    // a component on an entity that isn't alive
+    let e = ecs.get_entity_from_id(0).unwrap();
+    let mut json: serde_json::Value = serde_json::to_value(&ecs).unwrap();
+    ecs.despawn(e);
+    json[0] = serde_json::to_value(&ecs).unwrap()[0].clone();
+    std::fs::write(dir.join("broken.json"), json.to_string()).unwrap();
+    let (ok, out) = inspect(&dir, &["validate", "schema.json", "broken.json"]);
+    assert!(!ok);
+    assert!(out.contains("error: CName on 0, which isn't alive\n"));
+}
+
+#[test]
+fn malformed_saves_are_errors() {
+    let (dir, ecs) = fixture("malformed");
+    let json = serde_json::to_value(&ecs).unwrap();
+    // the dense array of the pool holding `CName("a")` is `[[index, value]]`
+    let pool = json
+        .as_array()
+        .unwrap()
+        .iter()
+        .position(|pool| pool[1][0][1] == "a")
+        .unwrap();
+
+    for (name, index) in [
+        ("negative", serde_json::json!(-1)),
+        ("float", serde_json::json!(0.5)),
+    ] {
+        let mut json = json.clone();
+        json[pool][1][0][0] = index;
+        std::fs::write(dir.join(format!("{name}.json")), json.to_string()).unwrap();
+        let (ok, out) = inspect(&dir, &["counts", "schema.json", &format!("{name}.json")]);
+        assert!(!ok);
+        assert!(out.starts_with("error: "), "{out}");
+    }
+}
@@ -1,40 +1,234 @@
 use heck::ToSnakeCase;
-use proc_macro::{Ident, TokenStream};
-use proc_macro_error::{abort, emit_error, emit_warning, proc_macro_error};
+use proc_macro::TokenStream;
+use proc_macro_error::{abort, proc_macro_error};
 use quote::quote;
 use syn::{
     parse::{Parse, ParseStream},
-    parse_macro_input, ItemStruct, Result, Visibility,
+    parse_macro_input, ItemStruct, Result,
 };
 
 struct ComponentDefs {
     s: Vec<ItemStruct>,
+    /// Options given through our own attributes, in the same order as `s`. The attributes are
+    /// stripped from `s` so it can be emitted as is.
+    attrs: Vec<ComponentAttrs>,
+}
+
+#[derive(Default)]
+struct ComponentAttrs {
+    /// `#[map_entities]` on the struct: the user implements `eliecs::MapEntities` themselves.
+    map_entities: bool,
+    /// Fields marked `#[entity]`, which get a generated `eliecs::MapEntities` impl.
+    entity_fields: Vec<syn::Member>,
+    /// `#[replicate]`: sent to clients by `eliecs::replication::Server`.
+    replicate: bool,
+    /// `#[no_clone]`: left out by `Ecs::clone_entity`, e.g. for unique ids or handles that mustn't
+    /// be shared. That's all it does: the component still implements `Clone`, since copying the
+    /// whole world for snapshots or undo copies it like any other.
+    no_clone: bool,
+    /// `PartialEq` is in one of the struct's `#[derive]`s. `Ecs`, `FatEntity` and
+    /// `ComponentTypeContaining` only implement `PartialEq` when every component derives it.
+    derives_eq: bool,
+    /// Indices of the fields seen through `eliecs::reflect::Reflect`, with the names serde gives
+    /// them. Tuple fields are named by their position among the serialized ones.
+    reflected_fields: Vec<(usize, String)>,
+}
+
+/// What `#[serde(..)]` attributes say about how a struct or field is serialized, as far as
+/// reflection cares.
+#[derive(Default)]
+struct SerdeAttrs {
+    /// `skip`, `skip_serializing` or `skip_deserializing`.
+    skip: bool,
+    /// A `with` that serializes the field in a way its type can't.
+    with: bool,
+    rename: Option<String>,
+    rename_all: Option<String>,
+}
+
+impl SerdeAttrs {
+    fn parse(attrs: &[syn::Attribute]) -> Self {
+        let mut serde = SerdeAttrs::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+            // anything we don't understand is for serde to complain about
+            let _ = attr.parse_nested_meta(|meta| {
+                let Some(name) = meta.path.get_ident().map(|ident| ident.to_string()) else {
+                    return Ok(());
+                };
+                match name.as_str() {
+                    "skip" | "skip_serializing" | "skip_deserializing" => serde.skip = true,
+                    "with" | "serialize_with" | "deserialize_with" => {
+                        serde.with = true;
+                        meta.value()?.parse::<syn::LitStr>()?;
+                    }
+                    "rename" | "rename_all" => {
+                        let mut value = None;
+                        if meta.input.peek(syn::Token![=]) {
+                            value = Some(meta.value()?.parse::<syn::LitStr>()?.value());
+                        } else {
+                            // `rename(serialize = "..", deserialize = "..")`
+                            meta.parse_nested_meta(|meta| {
+                                let v = meta.value()?.parse::<syn::LitStr>()?.value();
+                                if meta.path.is_ident("serialize") {
+                                    value = Some(v);
+                                }
+                                Ok(())
+                            })?;
+                        }
+                        if name == "rename" {
+                            serde.rename = value;
+                        } else {
+                            serde.rename_all = value;
+                        }
+                    }
+                    _ if meta.input.peek(syn::Token![=]) => {
+                        meta.value()?.parse::<syn::Expr>()?;
+                    }
+                    _ if meta.input.peek(syn::token::Paren) => {
+                        let content;
+                        syn::parenthesized!(content in meta.input);
+                        content.parse::<proc_macro2::TokenStream>()?;
+                    }
+                    _ => {}
+                }
+                Ok(())
+            });
+        }
+        serde
+    }
+
+    /// `field` renamed by `rename_all`, like serde does.
+    fn rename_field(&self, field: &str) -> String {
+        use heck::{
+            ToKebabCase, ToLowerCamelCase, ToShoutyKebabCase, ToShoutySnakeCase, ToUpperCamelCase,
+        };
+        match self.rename_all.as_deref() {
+            Some("lowercase") => field.to_lowercase(),
+            Some("UPPERCASE") => field.to_uppercase(),
+            Some("PascalCase") => field.to_upper_camel_case(),
+            Some("camelCase") => field.to_lower_camel_case(),
+            Some("SCREAMING_SNAKE_CASE") => field.to_shouty_snake_case(),
+            Some("kebab-case") => field.to_kebab_case(),
+            Some("SCREAMING-KEBAB-CASE") => field.to_shouty_kebab_case(),
+            _ => field.to_string(),
+        }
+    }
+}
+
+impl ComponentAttrs {
+    fn take(item: &mut ItemStruct) -> Self {
+        let mut attrs = ComponentAttrs::default();
+        item.attrs.retain(|attr| {
+            if attr.path().is_ident("map_entities") {
+                attrs.map_entities = true;
+                false
+            } else if attr.path().is_ident("replicate") {
+                attrs.replicate = true;
+                false
+            } else if attr.path().is_ident("no_clone") {
+                attrs.no_clone = true;
+                false
+            } else {
+                if attr.path().is_ident("derive") {
+                    let _ = attr.parse_nested_meta(|meta| {
+                        attrs.derives_eq |= meta
+                            .path
+                            .segments
+                            .last()
+                            .is_some_and(|segment| segment.ident == "PartialEq");
+                        Ok(())
+                    });
+                }
+                true
+            }
+        });
+        let container = SerdeAttrs::parse(&item.attrs);
+        // serde ignores `skip` on the field of a newtype struct
+        let newtype =
+            matches!(&item.fields, syn::Fields::Unnamed(fields) if fields.unnamed.len() == 1);
+        for (i, field) in item.fields.iter().enumerate() {
+            let serde = SerdeAttrs::parse(&field.attrs);
+            if serde.with || (serde.skip && !newtype) {
+                continue;
+            }
+            let name = match (serde.rename, &field.ident) {
+                (Some(name), _) => name,
+                (None, Some(ident)) => container.rename_field(&ident.to_string()),
+                (None, None) => attrs.reflected_fields.len().to_string(),
+            };
+            attrs.reflected_fields.push((i, name));
+        }
+        for (i, field) in item.fields.iter_mut().enumerate() {
+            let before = field.attrs.len();
+            field.attrs.retain(|attr| !attr.path().is_ident("entity"));
+            if field.attrs.len() != before {
+                attrs.entity_fields.push(match &field.ident {
+                    Some(ident) => syn::Member::Named(ident.clone()),
+                    None => syn::Member::Unnamed(syn::Index::from(i)),
+                });
+            }
+        }
+        if attrs.map_entities && !attrs.entity_fields.is_empty() {
+            abort!(
+                item.ident.span(),
+                "component has both #[map_entities] and #[entity] fields; use one or the other"
+            );
+        }
+        attrs
+    }
+
+    fn maps_entities(&self) -> bool {
+        self.map_entities || !self.entity_fields.is_empty()
+    }
 }
 
 impl Parse for ComponentDefs {
     fn parse(input: ParseStream) -> Result<Self> {
-        let mut s: Vec<ItemStruct> = Vec::new();
+        let mut s: Vec<(ItemStruct, ComponentAttrs)> = Vec::new();
         while !input.is_empty() {
-            s.push(input.parse()?);
-            if !s[s.len() - 1].ident.to_string().starts_with("C") {
-                abort!(
-                    s[s.len() - 1].ident.span(),
-                    "component's name does not start with C"
-                );
+            let mut item: ItemStruct = input.parse()?;
+            if !item.ident.to_string().starts_with("C") {
+                abort!(item.ident.span(), "component's name does not start with C");
             }
+            let attrs = ComponentAttrs::take(&mut item);
+            s.push((item, attrs));
         }
-        s.sort_by_key(|v| v.ident.to_string());
-        Ok(ComponentDefs { s })
+        s.sort_by_key(|v| v.0.ident.to_string());
+        let (s, attrs) = s.into_iter().unzip();
+        Ok(ComponentDefs { s, attrs })
     }
 }
 
+/// `CPosition` -> `position`, the name used for a component's pool and accessors.
+fn snake_ident(ident: &syn::Ident) -> proc_macro2::Ident {
+    proc_macro2::Ident::new(
+        &(ident.to_string().strip_prefix("C").unwrap()).to_snake_case(),
+        ident.span(),
+    )
+}
+
+/// A type as written in the source, e.g. `Vec<(Entity, u32)>`; `to_string` spaces every token.
+fn type_name(ty: &syn::Type) -> String {
+    let word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+    let mut name = String::new();
+    for token in quote!(#ty).to_string().split(' ') {
+        if (word(name.chars().last()) && word(token.chars().next()))
+            || name.ends_with(',')
+            || name.ends_with(';')
+        {
+            name.push(' ');
+        }
+        name.push_str(token);
+    }
+    name
+}
+
 #[proc_macro_error]
 #[proc_macro]
 pub fn components(input: TokenStream) -> TokenStream {
-    let input_parse = input.clone();
-    let components = parse_macro_input!(input_parse as ComponentDefs);
+    let components = parse_macro_input!(input as ComponentDefs);
 
-    let tokens: proc_macro2::TokenStream = input.into();
+    let tokens = &components.s;
 
     let fat_fields = components
         .s
@@ -49,6 +243,12 @@ pub fn components(input: TokenStream) -> TokenStream {
         })
         .collect::<Vec<_>>();
 
+    let fat_field_names = components
+        .s
+        .iter()
+        .map(|v| snake_ident(&v.ident))
+        .collect::<Vec<_>>();
+
     let fat_methods = components
         .s
         .iter()
@@ -114,7 +314,7 @@ pub fn components(input: TokenStream) -> TokenStream {
                 &(ident.to_string().strip_prefix("C").unwrap()).to_snake_case(),
                 ident.span(),
             );
-            let len = proc_macro2::Literal::usize_suffixed(i + 2);
+            let i = i + 2;
 
             quote! { let #renamed_ident = std::cell::UnsafeCell::new(
                 seq.next_element()?
@@ -126,8 +326,7 @@ pub fn components(input: TokenStream) -> TokenStream {
     let ecs_ser = components
         .s
         .iter()
-        .enumerate()
-        .map(|(i, v)| {
+        .map(|v| {
             let ident = &v.ident;
             let renamed_ident = proc_macro2::Ident::new(
                 &(ident.to_string().strip_prefix("C").unwrap()).to_snake_case(),
@@ -181,6 +380,14 @@ pub fn components(input: TokenStream) -> TokenStream {
 
         let error_message =
             proc_macro2::Literal::string(&format!("expected entity to have component {}", ident));
+        let component_name = ident.to_string();
+        let try_ident = proc_macro2::Ident::new(&format!("try_{renamed_ident}"), ident.span());
+        let try_mut_ident =
+            proc_macro2::Ident::new(&format!("try_{renamed_ident_mut}"), ident.span());
+        let try_add_ident =
+            proc_macro2::Ident::new(&format!("try_{add_renamed_ident}"), ident.span());
+        let try_remove_ident =
+            proc_macro2::Ident::new(&format!("try_{remove_renamed_ident}"), ident.span());
 
         quote! {
             pub fn #renamed_ident(&self, id: u32) -> Option<&#ident> {
@@ -193,31 +400,106 @@ pub fn components(input: TokenStream) -> TokenStream {
             }
 
             pub fn #renamed_ident_mut(&self, id: u32) -> Option<&mut #ident> {
-                unsafe { (*(self.#renamed_ident.get())).get_mut(id) }
+                let v = unsafe { (*(self.#renamed_ident.get())).get_mut(id) };
+                if let Some(v) = &v {
+                    self.record_write(id, || ComponentTypeContaining::#ident(#ident::clone(v)));
+                }
+                v
             }
 
             pub fn #renamed_ident_mut_unwrap(&self, id: u32) -> &mut #ident {
-                unsafe { (*(self.#renamed_ident.get())).get_mut(id) }
-                    .expect(#error_message)
+                self.#renamed_ident_mut(id).expect(#error_message)
             }
 
             pub fn #query_renamed_ident(&self) -> impl Iterator<Item = (u32, &#ident)> {
                 unsafe { &mut *(self.#renamed_ident.get()) }.iter()
             }
             pub fn #query_renamed_ident_mut(&self) -> impl Iterator<Item = (u32, &mut #ident)> {
+                if self.tracks_writes() {
+                    let written = unsafe { &mut *self.written.get() };
+                    for (id, v) in unsafe { &*(self.#renamed_ident.get()) }.iter() {
+                        written.push((id, ComponentTypeContaining::#ident(v.clone())));
+                    }
+                }
                 unsafe { &mut *(self.#renamed_ident.get()) }.iter_mut()
             }
 
             pub fn #add_renamed_ident(&self, id: u32, v: #ident) -> bool {
+                self.record(id, |e| {
+                    eliecs::Mutation::Insert(e, ComponentTypeContaining::#ident(v.clone()))
+                });
                 unsafe { &mut *(self.#renamed_ident.get()) }.insert(id, v)
             }
 
             pub fn #remove_renamed_ident(&self, id: u32) {
-                unsafe { &mut *(self.#renamed_ident.get()) }.remove(id);
+                if unsafe { &*(self.#renamed_ident.get()) }.contains(id) {
+                    self.record(id, |e| eliecs::Mutation::Remove(e, ComponentType::#ident));
+                    unsafe { &mut *(self.#renamed_ident.get()) }.remove(id);
+                }
+            }
+
+            /// Borrows the component, failing while it is borrowed through `try_*_mut`.
+            pub fn #try_ident(
+                &self,
+                e: eliecs::Entity,
+            ) -> Result<eliecs::ComponentRef<'_, #ident>, eliecs::EcsError> {
+                self.check_alive(e)?;
+                self.borrows.borrow(e, #component_name, || {
+                    self.#renamed_ident(e.id).ok_or(eliecs::EcsError::MissingComponent {
+                        entity: e,
+                        component: #component_name,
+                    })
+                })
+            }
+
+            /// Borrows the component mutably, failing while it is borrowed through any `try_*`
+            /// accessor.
+            pub fn #try_mut_ident(
+                &self,
+                e: eliecs::Entity,
+            ) -> Result<eliecs::ComponentRefMut<'_, #ident>, eliecs::EcsError> {
+                self.check_alive(e)?;
+                self.borrows.borrow_mut(e, #component_name, || {
+                    self.#renamed_ident_mut(e.id).ok_or(eliecs::EcsError::MissingComponent {
+                        entity: e,
+                        component: #component_name,
+                    })
+                })
+            }
+
+            /// Like the `add_` method, returning whether a component was replaced. Fails while
+            /// any component of this type is borrowed through a `try_*` accessor.
+            pub fn #try_add_ident(
+                &self,
+                e: eliecs::Entity,
+                v: #ident,
+            ) -> Result<bool, eliecs::EcsError> {
+                self.check_alive(e)?;
+                self.borrows.check_unborrowed(e, #component_name)?;
+                Ok(self.#add_renamed_ident(e.id, v))
+            }
+
+            /// Removes the component and returns it, with the same restriction as the `try_add_`
+            /// method.
+            pub fn #try_remove_ident(&self, e: eliecs::Entity) -> Result<#ident, eliecs::EcsError> {
+                self.check_alive(e)?;
+                if !unsafe { &*(self.#renamed_ident.get()) }.contains(e.id) {
+                    return Err(eliecs::EcsError::MissingComponent {
+                        entity: e,
+                        component: #component_name,
+                    });
+                }
+                self.borrows.check_unborrowed(e, #component_name)?;
+                self.record(e.id, |e| eliecs::Mutation::Remove(e, ComponentType::#ident));
+                Ok(unsafe { &mut *(self.#renamed_ident.get()) }.take(e.id).unwrap())
             }
         }
     });
 
+    let component_count = components.s.len();
+    let all_eq = components.attrs.iter().all(|attrs| attrs.derives_eq);
+    let derive_eq = all_eq.then(|| quote! { , PartialEq });
+    let mask_words = component_count.div_ceil(64).max(1);
     let ecs_tuple_size = proc_macro2::Literal::usize_suffixed(components.s.len() + 2);
 
     let component_types = components
@@ -236,6 +518,14 @@ pub fn components(input: TokenStream) -> TokenStream {
             quote! { #ident (#ident) }
         })
         .collect::<Vec<_>>();
+    let component_types_containing_ref = components
+        .s
+        .iter()
+        .map(|v| {
+            let ident = &v.ident;
+            quote! { #ident (&'a #ident) }
+        })
+        .collect::<Vec<_>>();
     let component_types_add_to_fat_entity = components
         .s
         .iter()
@@ -261,7 +551,10 @@ pub fn components(input: TokenStream) -> TokenStream {
             );
 
             quote! { if let Some(v) = data.#renamed_ident {
-                self.#renamed_ident.get_mut().insert(e.id, v);
+                self.record(id, |e| {
+                    eliecs::Mutation::Insert(e, ComponentTypeContaining::#ident(v.clone()))
+                });
+                self.#renamed_ident.get_mut().insert(id, v);
             } }
         })
         .collect::<Vec<_>>();
@@ -276,108 +569,2091 @@ pub fn components(input: TokenStream) -> TokenStream {
                 ident.span(),
             );
 
-            quote! {self.#renamed_ident.get_mut().remove(e.id);}
+            quote! { data.#renamed_ident = self.#renamed_ident.get_mut().take(e.id); }
         })
         .collect::<Vec<_>>();
 
-    quote! {
-        use eliecs::{Entity, Pool};
-        use serde::{
-            de::Visitor,
-            ser::{SerializeStruct, SerializeTuple},
-        };
-
-            #tokens
+    let scene_per_component = components
+        .s
+        .iter()
+        .map(|v| {
+            let ident = &v.ident;
+            let renamed_ident = snake_ident(ident);
 
-            #[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
-            pub enum ComponentType {
-                #(#component_types),*
-            }
+            quote! { if let Some(v) = self.#renamed_ident(id) {
+                components.push(ComponentTypeContaining::#ident(v.clone()));
+            } }
+        })
+        .collect::<Vec<_>>();
 
-            #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-            #[serde(tag = "type", content = "value")]
-            pub enum ComponentTypeContaining {
-                #(#component_types_containing),*
-            }
+    let component_map_entities_impls = components
+        .s
+        .iter()
+        .zip(&components.attrs)
+        .filter(|(_, attrs)| !attrs.entity_fields.is_empty())
+        .map(|(v, attrs)| {
+            let ident = &v.ident;
+            let fields = &attrs.entity_fields;
 
-            impl ComponentTypeContaining {
-                pub fn add_to_fat_entity(self, fat: FatEntity) -> FatEntity {
-                    match self {
-                        #(#component_types_add_to_fat_entity),*
+            quote! {
+                impl eliecs::MapEntities for #ident {
+                    fn map_entities(&mut self, map: &eliecs::EntityMap) {
+                        #(eliecs::MapEntities::map_entities(&mut self.#fields, map);)*
                     }
                 }
             }
+        })
+        .collect::<Vec<_>>();
 
-            #[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize)]
-            pub struct FatEntity {
-                #(#fat_fields),*
+    let containing_map_entities = components
+        .s
+        .iter()
+        .zip(&components.attrs)
+        .map(|(v, attrs)| {
+            let ident = &v.ident;
+            if attrs.maps_entities() {
+                quote! { Self::#ident(v) => eliecs::MapEntities::map_entities(v, map) }
+            } else {
+                quote! { Self::#ident(_) => {} }
             }
+        })
+        .collect::<Vec<_>>();
 
-            impl FatEntity {
-                pub fn new() -> Self {
-                    Self::default()
-                }
+    let fat_map_entities = components
+        .s
+        .iter()
+        .zip(&components.attrs)
+        .filter(|(_, attrs)| attrs.maps_entities())
+        .map(|(v, _)| {
+            let renamed_ident = snake_ident(&v.ident);
+            quote! { eliecs::MapEntities::map_entities(&mut self.#renamed_ident, map); }
+        })
+        .collect::<Vec<_>>();
 
-                #(#fat_methods)*
+    let append_per_component = components
+        .s
+        .iter()
+        .zip(&components.attrs)
+        .map(|(v, attrs)| {
+            let ident = &v.ident;
+            let renamed_ident = snake_ident(ident);
+            let map_entities = attrs
+                .maps_entities()
+                .then(|| quote! { eliecs::MapEntities::map_entities(&mut v, &map); });
+
+            quote! {
+                for (id, mut v) in #renamed_ident.into_inner() {
+                    // `add_*` can leave components on ids that aren't alive, those are dropped
+                    let Some(e) = existence
+                        .get(id)
+                        .and_then(|&version| map.get(Entity::new(id, version)))
+                    else {
+                        continue;
+                    };
+                    #map_entities
+                    self.record(e.id, |e| {
+                        eliecs::Mutation::Insert(e, ComponentTypeContaining::#ident(v.clone()))
+                    });
+                    self.#renamed_ident.get_mut().insert(e.id, v);
+                }
             }
+        })
+        .collect::<Vec<_>>();
 
-            pub struct Ecs {
-                existence: Pool<std::num::NonZeroU32>,
-                free_list: Vec<Entity>,
-                        #(#ecs_fields),*
+    let ecs_fields_clone = components
+        .s
+        .iter()
+        .map(|v| {
+            let renamed_ident = snake_ident(&v.ident);
+            quote! {
+                #renamed_ident: std::cell::UnsafeCell::new(
+                    unsafe { &*(self.#renamed_ident.get()) }.clone()
+                )
             }
+        })
+        .collect::<Vec<_>>();
 
-    #[allow(clippy::mut_from_ref)]
-    impl Ecs {
-        pub fn new() -> Self {
-            Self {
-                existence: Pool::new(),
-                free_list: Vec::new(),
-                #(#ecs_fields_init),*
+    let ecs_fields_clone_from = components
+        .s
+        .iter()
+        .map(|v| {
+            let renamed_ident = snake_ident(&v.ident);
+            quote! {
+                self.#renamed_ident
+                    .get_mut()
+                    .clone_from(unsafe { &*(source.#renamed_ident.get()) });
             }
-        }
-        pub fn is_alive(&self, e: eliecs::Entity) -> bool {
-            self.existence.get(e.id).copied() == Some(e.version)
-        }
-        pub fn get_entity_from_id(&self, id: u32) -> Option<Entity> {
-            if let Some(ver) = self.existence.get(id).copied() {
-                Some(Entity::new(id, ver))
-            } else {
-                None
+        })
+        .collect::<Vec<_>>();
+
+    let containing_component_type = components
+        .s
+        .iter()
+        .map(|v| {
+            let ident = &v.ident;
+            quote! { Self::#ident(_) => ComponentType::#ident }
+        })
+        .collect::<Vec<_>>();
+
+    let insert_component = components
+        .s
+        .iter()
+        .map(|v| {
+            let ident = &v.ident;
+            let renamed_ident = snake_ident(ident);
+            quote! {
+                ComponentTypeContaining::#ident(v) => self.#renamed_ident.get_mut().insert(id, v)
             }
-        }
-        pub fn spawn(&mut self, data: FatEntity) -> eliecs::Entity {
-            let e: eliecs::Entity;
-            if let Some(v) = self.free_list.pop() {
-                e = v;
-            } else {
-                e = eliecs::Entity::new(self.existence.len(), std::num::NonZeroU32::MIN);
+        })
+        .collect::<Vec<_>>();
+
+    let remove_component = components
+        .s
+        .iter()
+        .map(|v| {
+            let ident = &v.ident;
+            let renamed_ident = snake_ident(ident);
+            quote! { ComponentType::#ident => self.#renamed_ident.get_mut().remove(id) }
+        })
+        .collect::<Vec<_>>();
+
+    let snapshot_entity_fields = components
+        .s
+        .iter()
+        .map(|v| {
+            let renamed_ident = snake_ident(&v.ident);
+            quote! { #renamed_ident: self.#renamed_ident(e.id).cloned() }
+        })
+        .collect::<Vec<_>>();
+
+    let component_ids = components
+        .s
+        .iter()
+        .map(|v| {
+            let ident = &v.ident;
+            let renamed_ident = snake_ident(ident);
+            quote! {
+                ComponentType::#ident => {
+                    Box::new(unsafe { &*self.#renamed_ident.get() }.iter().map(|(id, _)| id))
+                }
             }
-            self.existence.insert(e.id, e.version);
+        })
+        .collect::<Vec<_>>();
 
-            #(#spawn_per_component)*
+    let component_count_of = components
+        .s
+        .iter()
+        .map(|v| {
+            let ident = &v.ident;
+            let renamed_ident = snake_ident(ident);
+            quote! { ComponentType::#ident => unsafe { &*self.#renamed_ident.get() }.len() }
+        })
+        .collect::<Vec<_>>();
 
-            e
-        }
+    let component_cloned = components
+        .s
+        .iter()
+        .map(|v| {
+            let ident = &v.ident;
+            let renamed_ident = snake_ident(ident);
+            quote! {
+                ComponentType::#ident => self
+                    .#renamed_ident(id)
+                    .map(|v| ComponentTypeContaining::#ident(v.clone()))
+            }
+        })
+        .collect::<Vec<_>>();
 
-        pub fn despawn(&mut self, e: eliecs::Entity) {
-            if self.is_alive(e) {
-                self.existence.remove(e.id);
+    let has_component = components
+        .s
+        .iter()
+        .map(|v| {
+            let ident = &v.ident;
+            let renamed_ident = snake_ident(ident);
+            quote! {
+                ComponentType::#ident => unsafe { &*self.#renamed_ident.get() }.contains(id)
+            }
+        })
+        .collect::<Vec<_>>();
 
-                #(#despawn_per_component)*
+    let ecs_fields_eq = components
+        .s
+        .iter()
+        .map(|v| {
+            let renamed_ident = snake_ident(&v.ident);
+            quote! {
+                && unsafe { *self.#renamed_ident.get() == *other.#renamed_ident.get() }
+            }
+        })
+        .collect::<Vec<_>>();
 
-                let mut v = e;
-                v.version = if let Some(v) = v.version.checked_add(1) {
-                    v
-                } else {
-                    std::num::NonZeroU32::MIN
-                };
-                self.free_list.push(v);
+    // only when every component derives `PartialEq`, so worlds of components that don't still
+    // compile
+    let ecs_eq = all_eq.then(|| {
+        quote! {
+            /// Worlds are equal when the same entities are alive with equal components and the
+            /// free lists match. The order of the pools' dense arrays doesn't matter.
+            ///
+            /// Only implemented when every component derives `PartialEq`.
+            impl PartialEq for Ecs {
+                fn eq(&self, other: &Self) -> bool {
+                    self.existence == other.existence
+                        && self.free_list == other.free_list
+                        #(#ecs_fields_eq)*
+                }
             }
         }
+    });
 
-        #(#ecs_per_component_methods)*
-    }
+    // without `PartialEq`, components are compared as they serialize
+    let differs = components
+        .attrs
+        .iter()
+        .map(|attrs| {
+            if attrs.derives_eq {
+                quote! { old_v != v }
+            } else {
+                quote! {
+                    match (eliecs::reflect::to_value(old_v), eliecs::reflect::to_value(v)) {
+                        (Ok(old_v), Ok(v)) => old_v != v,
+                        _ => true,
+                    }
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let diff_per_component = components
+        .s
+        .iter()
+        .zip(&differs)
+        .map(|(v, differ)| {
+            let ident = &v.ident;
+            let renamed_ident = snake_ident(ident);
+            quote! {
+                let (old_pool, new_pool) =
+                    unsafe { (&*old.#renamed_ident.get(), &*new.#renamed_ident.get()) };
+                for (id, v) in new_pool.iter() {
+                    // `add_*` can leave components on ids that aren't alive, those are skipped
+                    let Some(e) = new.get_entity_from_id(id) else {
+                        continue;
+                    };
+                    match old_pool.get(id) {
+                        Some(old_v) if old.is_alive(e) => {
+                            if #differ {
+                                delta.updated.push((e, ComponentTypeContaining::#ident(v.clone())));
+                            }
+                        }
+                        _ => delta.inserted.push((e, ComponentTypeContaining::#ident(v.clone()))),
+                    }
+                }
+                for (id, _) in old_pool.iter() {
+                    let Some(e) = old.get_entity_from_id(id) else {
+                        continue;
+                    };
+                    if new.is_alive(e) && !new_pool.contains(id) {
+                        delta.removed.push((e, ComponentType::#ident));
+                    }
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let replication_per_component = components
+        .s
+        .iter()
+        .zip(&components.attrs)
+        .filter(|(_, attrs)| attrs.replicate)
+        .map(|(v, _)| {
+            let ident = &v.ident;
+            let renamed_ident = snake_ident(ident);
+            quote! {
+                for (id, v) in unsafe { &*self.#renamed_ident.get() }.iter() {
+                    let Some(e) = self.get_entity_from_id(id).filter(|_| sent.contains(id)) else {
+                        continue;
+                    };
+                    let hash =
+                        eliecs::hash::stable_hash(v).expect("hashing a replicated component");
+                    match known.hash(e, ComponentType::#ident) {
+                        None => delta
+                            .inserted
+                            .push((e, ComponentTypeContaining::#ident(v.clone()))),
+                        Some(known_hash) if known_hash != hash => delta
+                            .updated
+                            .push((e, ComponentTypeContaining::#ident(v.clone()))),
+                        Some(_) => {}
+                    }
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let has_replicated = components
+        .s
+        .iter()
+        .zip(&components.attrs)
+        .filter(|(_, attrs)| attrs.replicate)
+        .map(|(v, _)| {
+            let renamed_ident = snake_ident(&v.ident);
+            quote! { unsafe { &*self.#renamed_ident.get() }.contains(id) }
+        })
+        .collect::<Vec<_>>();
+
+    let component_type_maps_entities = components
+        .s
+        .iter()
+        .zip(&components.attrs)
+        .map(|(v, attrs)| {
+            let ident = &v.ident;
+            let maps_entities = attrs.maps_entities();
+            quote! { Self::#ident => #maps_entities }
+        })
+        .collect::<Vec<_>>();
+
+    let component_type_replicated = components
+        .s
+        .iter()
+        .zip(&components.attrs)
+        .map(|(v, attrs)| {
+            let ident = &v.ident;
+            let replicate = attrs.replicate;
+            quote! { Self::#ident => #replicate }
+        })
+        .collect::<Vec<_>>();
+
+    let component_type_names = components
+        .s
+        .iter()
+        .map(|v| {
+            let ident = &v.ident;
+            let name = ident.to_string().strip_prefix("C").unwrap().to_string();
+            quote! { Self::#ident => #name }
+        })
+        .collect::<Vec<_>>();
+
+    let component_type_from_name = components
+        .s
+        .iter()
+        .map(|v| {
+            let ident = &v.ident;
+            let full = ident.to_string();
+            let name = full.strip_prefix("C").unwrap().to_string();
+            let snake = snake_ident(ident).to_string();
+            quote! { #name | #full | #snake => Some(Self::#ident) }
+        })
+        .collect::<Vec<_>>();
+
+    let component_value = components
+        .s
+        .iter()
+        .map(|v| {
+            let ident = &v.ident;
+            let renamed_ident = snake_ident(ident);
+            quote! {
+                ComponentType::#ident => self
+                    .#renamed_ident(id)
+                    .map(|v| eliecs::reflect::to_value(v))
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let component_memory_stats = components
+        .s
+        .iter()
+        .map(|v| {
+            let ident = &v.ident;
+            let renamed_ident = snake_ident(ident);
+            quote! {
+                (
+                    ComponentType::#ident,
+                    unsafe { &*self.#renamed_ident.get() }.memory_stats(),
+                )
+            }
+        })
+        .collect::<Vec<_>>();
+    let pool_idents = components
+        .s
+        .iter()
+        .map(|v| snake_ident(&v.ident))
+        .collect::<Vec<_>>();
+
+    let component_debug = components
+        .s
+        .iter()
+        .map(|v| {
+            let ident = &v.ident;
+            let renamed_ident = snake_ident(ident);
+            quote! {
+                ComponentType::#ident => self
+                    .#renamed_ident(id)
+                    .map(|v| v as &dyn std::fmt::Debug)
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // fields serde skips aren't reflected, so they needn't implement `Serialize`
+    let reflect_impls = components
+        .s
+        .iter()
+        .zip(&components.attrs)
+        .map(|(v, attrs)| {
+            let ident = &v.ident;
+            let fields = v.fields.iter().collect::<Vec<_>>();
+            let members = attrs
+                .reflected_fields
+                .iter()
+                .map(|&(i, _)| match &fields[i].ident {
+                    Some(ident) => syn::Member::Named(ident.clone()),
+                    None => syn::Member::Unnamed(syn::Index::from(i)),
+                })
+                .collect::<Vec<_>>();
+            let names = attrs
+                .reflected_fields
+                .iter()
+                .map(|(_, name)| name)
+                .collect::<Vec<_>>();
+            let types = attrs
+                .reflected_fields
+                .iter()
+                .map(|&(i, _)| type_name(&fields[i].ty));
+            quote! {
+                impl eliecs::reflect::Reflect for #ident {
+                    const FIELDS: &'static [eliecs::reflect::FieldInfo] = &[
+                        #(eliecs::reflect::FieldInfo {
+                            name: #names,
+                            ty: #types,
+                            offset: std::mem::offset_of!(#ident, #members),
+                        }),*
+                    ];
+
+                    fn field(
+                        &self,
+                        name: &str,
+                    ) -> Option<Result<eliecs::reflect::Value, eliecs::reflect::ReflectError>> {
+                        match name {
+                            #(#names => Some(eliecs::reflect::to_value(&self.#members)),)*
+                            _ => None,
+                        }
+                    }
+
+                    #[allow(unused_variables)]
+                    fn set_field(
+                        &mut self,
+                        name: &str,
+                        value: eliecs::reflect::Value,
+                    ) -> Result<(), eliecs::reflect::ReflectError> {
+                        match name {
+                            #(#names => self.#members = eliecs::reflect::from_value(value)?,)*
+                            _ => {
+                                return Err(eliecs::reflect::ReflectError(format!(
+                                    "no field `{name}`"
+                                )))
+                            }
+                        }
+                        Ok(())
+                    }
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let component_schemas = components
+        .s
+        .iter()
+        .zip(&components.attrs)
+        .enumerate()
+        .map(|(index, (v, attrs))| {
+            let ident = &v.ident;
+            let name = ident.to_string();
+            let snake_name = snake_ident(ident).to_string();
+            let index = index as u32;
+            let repr = match &v.fields {
+                syn::Fields::Named(_) => quote!(Struct),
+                syn::Fields::Unnamed(fields) if fields.unnamed.len() == 1 => quote!(Newtype),
+                syn::Fields::Unnamed(_) => quote!(Tuple),
+                syn::Fields::Unit => quote!(Unit),
+            };
+            let replicate = attrs.replicate;
+            let cloned = !attrs.no_clone;
+            quote! {
+                eliecs::schema::ComponentSchema::new(
+                    #name,
+                    #snake_name,
+                    #index,
+                    eliecs::schema::Repr::#repr,
+                    <#ident as eliecs::reflect::Reflect>::FIELDS,
+                    #replicate,
+                    #cloned,
+                )
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let component_type_cloned = components
+        .s
+        .iter()
+        .zip(&components.attrs)
+        .map(|(v, attrs)| {
+            let ident = &v.ident;
+            let cloned = !attrs.no_clone;
+            quote! { Self::#ident => #cloned }
+        })
+        .collect::<Vec<_>>();
+
+    let state_hash_per_component = components
+        .s
+        .iter()
+        .map(|v| {
+            let ident = &v.ident;
+            let renamed_ident = snake_ident(ident);
+            quote! {
+                (
+                    ComponentType::#ident,
+                    eliecs::hash::hash_pool(unsafe { &*self.#renamed_ident.get() })?,
+                )
+            }
+        })
+        .collect::<Vec<_>>();
+
+    quote! {
+        use eliecs::{Entity, Pool};
+        use serde::{
+            de::Visitor,
+            ser::{SerializeStruct, SerializeTuple},
+        };
+
+            #(#tokens)*
+
+            #[derive(
+                Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord,
+                serde::Serialize, serde::Deserialize,
+            )]
+            pub enum ComponentType {
+                #(#component_types),*
+            }
+
+            #[derive(Debug, Clone #derive_eq)]
+            pub enum ComponentTypeContaining {
+                #(#component_types_containing),*
+            }
+
+            // Human readable formats get `{"type": .., "value": ..}`, which is what people edit.
+            // Binary formats like bincode can't deserialize that, so they get the plain
+            // externally tagged enum instead.
+            impl serde::Serialize for ComponentTypeContaining {
+                fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                where
+                    S: serde::Serializer,
+                {
+                    #[derive(serde::Serialize)]
+                    #[serde(tag = "type", content = "value", rename = "ComponentTypeContaining")]
+                    enum Tagged<'a> {
+                        #(#component_types_containing_ref),*
+                    }
+                    #[derive(serde::Serialize)]
+                    #[serde(rename = "ComponentTypeContaining")]
+                    enum Indexed<'a> {
+                        #(#component_types_containing_ref),*
+                    }
+
+                    if serializer.is_human_readable() {
+                        match self {
+                            #(Self::#component_types(v) => Tagged::#component_types(v)),*
+                        }
+                        .serialize(serializer)
+                    } else {
+                        match self {
+                            #(Self::#component_types(v) => Indexed::#component_types(v)),*
+                        }
+                        .serialize(serializer)
+                    }
+                }
+            }
+
+            impl<'de> serde::Deserialize<'de> for ComponentTypeContaining {
+                fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+                where
+                    D: serde::Deserializer<'de>,
+                {
+                    #[derive(serde::Deserialize)]
+                    #[serde(tag = "type", content = "value", rename = "ComponentTypeContaining")]
+                    enum Tagged {
+                        #(#component_types_containing),*
+                    }
+                    #[derive(serde::Deserialize)]
+                    #[serde(rename = "ComponentTypeContaining")]
+                    enum Indexed {
+                        #(#component_types_containing),*
+                    }
+
+                    Ok(if deserializer.is_human_readable() {
+                        match Tagged::deserialize(deserializer)? {
+                            #(Tagged::#component_types(v) => Self::#component_types(v)),*
+                        }
+                    } else {
+                        match Indexed::deserialize(deserializer)? {
+                            #(Indexed::#component_types(v) => Self::#component_types(v)),*
+                        }
+                    })
+                }
+            }
+
+            #(#component_map_entities_impls)*
+
+            #(#reflect_impls)*
+
+            impl ComponentType {
+                pub const ALL: [ComponentType; #component_count] =
+                    [#(ComponentType::#component_types),*];
+
+                /// Whether the component was marked `#[replicate]`.
+                pub fn is_replicated(self) -> bool {
+                    match self {
+                        #(#component_type_replicated),*
+                    }
+                }
+
+                /// Whether the component holds entities, through `#[entity]` fields or
+                /// `#[map_entities]`.
+                fn maps_entities(self) -> bool {
+                    match self {
+                        #(#component_type_maps_entities),*
+                    }
+                }
+
+                /// The component's fields, see [`eliecs::reflect::Reflect`].
+                pub fn fields(self) -> &'static [eliecs::reflect::FieldInfo] {
+                    match self {
+                        #(Self::#component_types => {
+                            <#component_types as eliecs::reflect::Reflect>::FIELDS
+                        }),*
+                    }
+                }
+
+                /// The component's name without the `C`, e.g. `Position` for `CPosition`.
+                pub fn name(self) -> &'static str {
+                    match self {
+                        #(#component_type_names),*
+                    }
+                }
+
+                /// The type named `name`, which can be written like `Position`, `CPosition` or
+                /// `position`.
+                pub fn from_name(name: &str) -> Option<Self> {
+                    match name {
+                        #(#component_type_from_name,)*
+                        _ => None,
+                    }
+                }
+
+                /// Whether [`Ecs::clone_entity`] copies the component, i.e. it wasn't marked
+                /// `#[no_clone]`.
+                pub fn is_cloned(self) -> bool {
+                    match self {
+                        #(#component_type_cloned),*
+                    }
+                }
+            }
+
+            /// A set of component types, e.g. the components [`Ecs::apply_fat_masked`] removes.
+            #[derive(
+                Debug, Clone, Copy, Default, PartialEq, Eq, Hash,
+                serde::Serialize, serde::Deserialize,
+            )]
+            pub struct ComponentMask([u64; #mask_words]);
+
+            impl ComponentMask {
+                pub fn new() -> Self {
+                    Self::default()
+                }
+
+                pub fn with(mut self, ty: ComponentType) -> Self {
+                    self.insert(ty);
+                    self
+                }
+
+                pub fn insert(&mut self, ty: ComponentType) {
+                    self.0[ty as usize / 64] |= 1 << (ty as usize % 64);
+                }
+
+                pub fn remove(&mut self, ty: ComponentType) {
+                    self.0[ty as usize / 64] &= !(1 << (ty as usize % 64));
+                }
+
+                pub fn contains(&self, ty: ComponentType) -> bool {
+                    self.0[ty as usize / 64] & (1 << (ty as usize % 64)) != 0
+                }
+
+                pub fn is_empty(&self) -> bool {
+                    self.0.iter().all(|&word| word == 0)
+                }
+
+                /// The types in the mask, in [`ComponentType::ALL`] order.
+                pub fn iter(&self) -> impl Iterator<Item = ComponentType> + '_ {
+                    ComponentType::ALL
+                        .into_iter()
+                        .filter(|&ty| self.contains(ty))
+                }
+            }
+
+            impl FromIterator<ComponentType> for ComponentMask {
+                fn from_iter<I: IntoIterator<Item = ComponentType>>(iter: I) -> Self {
+                    let mut mask = Self::new();
+                    for ty in iter {
+                        mask.insert(ty);
+                    }
+                    mask
+                }
+            }
+
+            impl eliecs::MapEntities for ComponentTypeContaining {
+                #[allow(unused_variables)]
+                fn map_entities(&mut self, map: &eliecs::EntityMap) {
+                    match self {
+                        #(#containing_map_entities),*
+                    }
+                }
+            }
+
+            impl ComponentTypeContaining {
+                pub fn component_type(&self) -> ComponentType {
+                    match self {
+                        #(#containing_component_type),*
+                    }
+                }
+
+                pub fn is_replicated(&self) -> bool {
+                    self.component_type().is_replicated()
+                }
+
+                /// Whether the two hold different components or different values, which works
+                /// without `PartialEq` on the components.
+                fn differs(&self, other: &Self) -> bool {
+                    match (other, self) {
+                        #((Self::#component_types(old_v), Self::#component_types(v)) => #differs,)*
+                        #[allow(unreachable_patterns)]
+                        _ => true,
+                    }
+                }
+
+                /// The field at `path` of the component, see
+                /// [`eliecs::reflect::Reflect::get_path`].
+                pub fn get_path(
+                    &self,
+                    path: &str,
+                ) -> Result<eliecs::reflect::Value, eliecs::reflect::ReflectError> {
+                    use eliecs::reflect::Reflect;
+                    match self {
+                        #(Self::#component_types(v) => v.get_path(path)),*
+                    }
+                }
+
+                pub fn set_path(
+                    &mut self,
+                    path: &str,
+                    value: eliecs::reflect::Value,
+                ) -> Result<(), eliecs::reflect::ReflectError> {
+                    use eliecs::reflect::Reflect;
+                    match self {
+                        #(Self::#component_types(v) => v.set_path(path, value)),*
+                    }
+                }
+
+                /// The component as a [`eliecs::reflect::Value`].
+                pub fn to_value(
+                    &self,
+                ) -> Result<eliecs::reflect::Value, eliecs::reflect::ReflectError> {
+                    match self {
+                        #(Self::#component_types(v) => eliecs::reflect::to_value(v)),*
+                    }
+                }
+
+                /// A component of type `ty` out of a [`eliecs::reflect::Value`], see
+                /// [`eliecs::reflect::from_value`].
+                pub fn from_value(
+                    ty: ComponentType,
+                    value: eliecs::reflect::Value,
+                ) -> Result<Self, eliecs::reflect::ReflectError> {
+                    match ty {
+                        #(ComponentType::#component_types => {
+                            eliecs::reflect::from_value(value).map(Self::#component_types)
+                        })*
+                    }
+                }
+
+                pub fn add_to_fat_entity(self, fat: FatEntity) -> FatEntity {
+                    match self {
+                        #(#component_types_add_to_fat_entity),*
+                    }
+                }
+            }
+
+            #[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize #derive_eq)]
+            pub struct FatEntity {
+                #(#fat_fields),*
+            }
+
+            impl FatEntity {
+                pub fn new() -> Self {
+                    Self::default()
+                }
+
+                #(#fat_methods)*
+
+                /// `self` with every component that `other` has replaced by `other`'s.
+                pub fn merge(self, other: FatEntity) -> FatEntity {
+                    FatEntity {
+                        #(#fat_field_names: other.#fat_field_names.or(self.#fat_field_names)),*
+                    }
+                }
+            }
+
+            impl eliecs::Template for FatEntity {
+                fn merge(self, overrides: Self) -> Self {
+                    FatEntity::merge(self, overrides)
+                }
+            }
+
+            impl eliecs::MapEntities for FatEntity {
+                #[allow(unused_variables)]
+                fn map_entities(&mut self, map: &eliecs::EntityMap) {
+                    #(#fat_map_entities)*
+                }
+            }
+
+            pub type Scene = eliecs::Scene<ComponentTypeContaining>;
+            pub type WorldDelta = eliecs::WorldDelta<ComponentTypeContaining, ComponentType>;
+            pub type StateHashes = eliecs::hash::StateHashes<ComponentType>;
+            pub type EcsStats = eliecs::EcsStats<ComponentType>;
+
+            pub type DynQuery = eliecs::DynQuery<ComponentType>;
+            pub type TextQuery = eliecs::TextQuery<ComponentType>;
+            pub type Prefab = eliecs::Prefab<FatEntity>;
+            pub type Prefabs = eliecs::Prefabs<FatEntity>;
+            pub type Journal = eliecs::Journal<ComponentTypeContaining, ComponentType>;
+            pub type History = eliecs::History<ComponentTypeContaining, ComponentType>;
+            pub type Mutation = eliecs::Mutation<ComponentTypeContaining, ComponentType>;
+
+            pub struct Ecs {
+                existence: Pool<std::num::NonZeroU32>,
+                free_list: Vec<Entity>,
+                /// Despawns that wrapped an id's version around, reported by `stats`.
+                version_wraparounds: u64,
+                prefabs: std::sync::Arc<Prefabs>,
+                journal: std::cell::UnsafeCell<Option<Journal>>,
+                history: std::cell::UnsafeCell<History>,
+                /// Values of components handed out mutably while writes are tracked.
+                written: std::cell::UnsafeCell<Vec<(u32, ComponentTypeContaining)>>,
+                /// Components handed out by the `try_*` accessors.
+                borrows: eliecs::BorrowFlags,
+                        #(#ecs_fields),*
+            }
+
+    #[allow(clippy::mut_from_ref)]
+    impl Ecs {
+        pub fn new() -> Self {
+            Self {
+                existence: Pool::new(),
+                free_list: Vec::new(),
+                version_wraparounds: 0,
+                prefabs: std::sync::Arc::default(),
+                journal: std::cell::UnsafeCell::new(None),
+                history: std::cell::UnsafeCell::new(History::new()),
+                written: std::cell::UnsafeCell::new(Vec::new()),
+                borrows: eliecs::BorrowFlags::new(),
+                #(#ecs_fields_init),*
+            }
+        }
+        pub fn is_alive(&self, e: eliecs::Entity) -> bool {
+            self.existence.get(e.id).copied() == Some(e.version)
+        }
+        pub fn get_entity_from_id(&self, id: u32) -> Option<Entity> {
+            if let Some(ver) = self.existence.get(id).copied() {
+                Some(Entity::new(id, ver))
+            } else {
+                None
+            }
+        }
+        pub fn spawn(&mut self, data: FatEntity) -> eliecs::Entity {
+            let e = self.alloc_entity();
+            self.insert_fat(e.id, data);
+            e
+        }
+
+        /// Like [`Ecs::spawn`], but fails instead of handing out an id past `u32::MAX`.
+        pub fn try_spawn(&mut self, data: FatEntity) -> Result<eliecs::Entity, eliecs::EcsError> {
+            if self.free_list.is_empty() && self.existence.len() == u32::MAX {
+                return Err(eliecs::EcsError::CapacityExceeded);
+            }
+            Ok(self.spawn(data))
+        }
+
+        /// Like [`Ecs::take`], failing if `e` is dead.
+        pub fn try_despawn(&mut self, e: eliecs::Entity) -> Result<FatEntity, eliecs::EcsError> {
+            self.take(e).ok_or(eliecs::EcsError::DeadEntity(e))
+        }
+
+        fn check_alive(&self, e: eliecs::Entity) -> Result<(), eliecs::EcsError> {
+            if self.is_alive(e) {
+                Ok(())
+            } else {
+                Err(eliecs::EcsError::DeadEntity(e))
+            }
+        }
+
+        fn alloc_entity(&mut self) -> eliecs::Entity {
+            let e: eliecs::Entity;
+            if let Some(v) = self.free_list.pop() {
+                e = v;
+            } else {
+                e = eliecs::Entity::new(self.existence.len(), std::num::NonZeroU32::MIN);
+            }
+            self.existence.insert(e.id, e.version);
+            self.record(e.id, eliecs::Mutation::Spawn);
+            e
+        }
+
+        /// A clone of the component of type `ty` of `id`, for tools that only know the type at
+        /// runtime. Like the other dynamic accessors, it mirrors the generated `x`/`add_x`/
+        /// `remove_x` methods.
+        pub fn get_dyn(&self, id: u32, ty: ComponentType) -> Option<ComponentTypeContaining> {
+            self.component_cloned(id, ty)
+        }
+
+        /// Inserts `c` into `id`, returning whether it replaced a component of the same type.
+        pub fn insert_dyn(&mut self, id: u32, c: ComponentTypeContaining) -> bool {
+            self.insert_component(id, c)
+        }
+
+        /// Removes the component of type `ty` of `id`, returning whether there was one.
+        pub fn remove_dyn(&mut self, id: u32, ty: ComponentType) -> bool {
+            self.remove_component(id, ty)
+        }
+
+        pub fn has_dyn(&self, id: u32, ty: ComponentType) -> bool {
+            self.has_component(id, ty)
+        }
+
+        /// The entities matching `query`, found by going through the smallest pool it includes,
+        /// or through every entity if it doesn't include anything.
+        pub fn query_dyn<'a>(
+            &'a self,
+            query: &'a DynQuery,
+        ) -> impl Iterator<Item = eliecs::Entity> + 'a {
+            let ids = match query
+                .include()
+                .iter()
+                .copied()
+                .min_by_key(|&ty| self.component_len(ty))
+            {
+                Some(ty) => self.component_ids(ty),
+                None => Box::new(self.existence.iter().map(|(id, _)| id)),
+            };
+            ids.filter(move |&id| query.matches(|ty| self.has_component(id, ty)))
+                .filter_map(move |id| self.get_entity_from_id(id))
+        }
+
+        /// Like [`Ecs::query_dyn`], along with clones of each entity's components of the types
+        /// `query` includes, in the same order.
+        pub fn query_dyn_fetch<'a>(
+            &'a self,
+            query: &'a DynQuery,
+        ) -> impl Iterator<Item = (eliecs::Entity, Vec<ComponentTypeContaining>)> + 'a {
+            self.query_dyn(query).map(move |e| {
+                let components = query
+                    .include()
+                    .iter()
+                    .map(|&ty| self.component_cloned(e.id, ty).unwrap())
+                    .collect();
+                (e, components)
+            })
+        }
+
+        /// A description of the components, for tools that read and write saves without
+        /// linking the game. See [`eliecs::schema`].
+        pub fn schema() -> eliecs::schema::Schema {
+            eliecs::schema::Schema::new(vec![#(#component_schemas),*])
+        }
+
+        /// The field at `path` of the component of type `ty` of `id`, e.g. `"x"` of `CPosition`.
+        pub fn get_field(
+            &self,
+            id: u32,
+            ty: ComponentType,
+            path: &str,
+        ) -> Result<eliecs::reflect::Value, eliecs::reflect::ReflectError> {
+            self.component_cloned(id, ty)
+                .ok_or_else(|| {
+                    eliecs::reflect::ReflectError(format!("{id} has no {}", ty.name()))
+                })?
+                .get_path(path)
+        }
+
+        /// Sets the field at `path` of the component of type `ty` of `id`. This is recorded like
+        /// inserting the whole component.
+        pub fn set_field(
+            &mut self,
+            id: u32,
+            ty: ComponentType,
+            path: &str,
+            value: eliecs::reflect::Value,
+        ) -> Result<(), eliecs::reflect::ReflectError> {
+            let mut c = self.component_cloned(id, ty).ok_or_else(|| {
+                eliecs::reflect::ReflectError(format!("{id} has no {}", ty.name()))
+            })?;
+            c.set_path(path, value)?;
+            self.insert_component(id, c);
+            Ok(())
+        }
+
+        /// The entities matching a query of the debug console's language, see
+        /// [`eliecs::TextQuery`].
+        pub fn query_text(&self, src: &str) -> Result<Vec<eliecs::Entity>, eliecs::QueryError> {
+            let query = TextQuery::parse(src, ComponentType::from_name)?;
+            let mut found = Vec::new();
+            for e in self.query_dyn(&query.query) {
+                if query.check(|ty| self.component_value(e.id, ty))? {
+                    found.push(e);
+                }
+            }
+            Ok(found)
+        }
+
+        fn component_value(
+            &self,
+            id: u32,
+            ty: ComponentType,
+        ) -> Option<Result<eliecs::reflect::Value, eliecs::reflect::ReflectError>> {
+            match ty {
+                #(#component_value),*
+            }
+        }
+
+        fn component_debug(&self, id: u32, ty: ComponentType) -> Option<&dyn std::fmt::Debug> {
+            match ty {
+                #(#component_debug),*
+            }
+        }
+
+        /// Alive entities by id, as they are listed by the `Debug` impl and `dump_table`.
+        fn sorted_entities(&self) -> Vec<eliecs::Entity> {
+            let mut entities = self
+                .existence
+                .iter()
+                .map(|(id, &version)| eliecs::Entity::new(id, version))
+                .collect::<Vec<_>>();
+            entities.sort_by_key(|e| e.id);
+            entities
+        }
+
+        fn fmt_components(&self, id: u32, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            let mut components = ComponentType::ALL
+                .iter()
+                .filter_map(|&ty| self.component_debug(id, ty))
+                .peekable();
+            if components.peek().is_none() {
+                return f.write_str("()");
+            }
+            while let Some(c) = components.next() {
+                write!(f, "{c:?}")?;
+                if components.peek().is_some() {
+                    f.write_str(", ")?;
+                }
+            }
+            Ok(())
+        }
+
+        /// The `columns` components of every entity having at least one of them, or of every
+        /// entity if `columns` is empty, with the `Debug` format of the components. Only the first
+        /// `max_rows` entities by id are listed.
+        pub fn dump_table(&self, columns: &[ComponentType], max_rows: usize) -> eliecs::Table {
+            let entities = self
+                .sorted_entities()
+                .into_iter()
+                .filter(|e| {
+                    columns.is_empty() || columns.iter().any(|&ty| self.has_component(e.id, ty))
+                })
+                .collect::<Vec<_>>();
+            let mut table = eliecs::Table::new(
+                std::iter::once("entity").chain(columns.iter().map(|ty| ty.name())),
+            );
+            for e in entities.iter().take(max_rows) {
+                table.push(std::iter::once(format!("{e:?}")).chain(columns.iter().map(|&ty| {
+                    match self.component_debug(e.id, ty) {
+                        Some(c) => format!("{c:?}"),
+                        None => "-".to_string(),
+                    }
+                })));
+            }
+            table.omitted = entities.len().saturating_sub(max_rows);
+            table
+        }
+
+        /// How much memory the pools use and how full they are.
+        pub fn stats(&self) -> EcsStats {
+            EcsStats {
+                alive: self.existence.len(),
+                free: self.free_list.len(),
+                version_wraparounds: self.version_wraparounds,
+                existence: self.existence.memory_stats(),
+                components: vec![#(#component_memory_stats),*],
+            }
+        }
+
+        /// Frees memory the pools and the free list don't use, e.g. after despawning many
+        /// entities. The next spawns and inserts allocate again.
+        pub fn shrink_to_fit(&mut self) {
+            self.existence.shrink_to_fit();
+            self.free_list.shrink_to_fit();
+            #(self.#pool_idents.get_mut().shrink_to_fit();)*
+        }
+
+        fn component_ids(&self, ty: ComponentType) -> Box<dyn Iterator<Item = u32> + '_> {
+            match ty {
+                #(#component_ids),*
+            }
+        }
+
+        fn component_len(&self, ty: ComponentType) -> u32 {
+            match ty {
+                #(#component_count_of),*
+            }
+        }
+
+        /// Inserts every component `fat` has into `e`, replacing the ones `e` already has.
+        /// Returns `false`, changing nothing, if `e` is dead.
+        pub fn apply_fat(&mut self, e: eliecs::Entity, fat: FatEntity) -> bool {
+            self.apply_fat_masked(e, fat, ComponentMask::new())
+        }
+
+        /// Like [`Ecs::apply_fat`], but first removes the components in `remove`, so a single
+        /// partial update can both set and clear components. A type both in `remove` and in `fat`
+        /// ends up with `fat`'s value.
+        pub fn apply_fat_masked(
+            &mut self,
+            e: eliecs::Entity,
+            fat: FatEntity,
+            remove: ComponentMask,
+        ) -> bool {
+            if !self.is_alive(e) {
+                return false;
+            }
+            for ty in remove.iter() {
+                self.remove_component(e.id, ty);
+            }
+            self.insert_fat(e.id, fat);
+            true
+        }
+
+        fn insert_fat(&mut self, id: u32, data: FatEntity) {
+            #(#spawn_per_component)*
+        }
+
+        /// Makes `e` alive, taking its id out of the free list if it is there.
+        /// Spawns exactly `e`, unless its id is already alive, maybe with another version.
+        fn spawn_at(&mut self, e: eliecs::Entity) -> bool {
+            if self.existence.contains(e.id) {
+                return false;
+            }
+            self.free_list.retain(|v| v.id != e.id);
+            self.existence.insert(e.id, e.version);
+            self.record(e.id, eliecs::Mutation::Spawn);
+            true
+        }
+
+        fn insert_component(&mut self, id: u32, c: ComponentTypeContaining) -> bool {
+            self.record(id, |e| eliecs::Mutation::Insert(e, c.clone()));
+            match c {
+                #(#insert_component),*
+            }
+        }
+
+        fn remove_component(&mut self, id: u32, ty: ComponentType) -> bool {
+            if !self.has_component(id, ty) {
+                return false;
+            }
+            self.record(id, |e| eliecs::Mutation::Remove(e, ty));
+            match ty {
+                #(#remove_component),*
+            }
+        }
+
+        fn has_component(&self, id: u32, ty: ComponentType) -> bool {
+            match ty {
+                #(#has_component),*
+            }
+        }
+
+        /// Clones the component of type `ty` of `id`.
+        fn component_cloned(&self, id: u32, ty: ComponentType) -> Option<ComponentTypeContaining> {
+            match ty {
+                #(#component_cloned),*
+            }
+        }
+
+        /// Spawns a copy of `e`, with a clone of each of its components not marked `#[no_clone]`.
+        /// Entities referenced by the components are kept as they are. Returns `None` if `e` is
+        /// dead.
+        pub fn clone_entity(&mut self, e: eliecs::Entity) -> Option<eliecs::Entity> {
+            if !self.is_alive(e) {
+                return None;
+            }
+            let copy = self.alloc_entity();
+            self.clone_entity_into(e, copy);
+            Some(copy)
+        }
+
+        /// Clones the components of `src` not marked `#[no_clone]` onto `dst`, replacing the
+        /// ones `dst` already has. Components only `dst` has are kept. Returns `false`, changing
+        /// nothing, if either entity is dead.
+        pub fn clone_entity_into(&mut self, src: eliecs::Entity, dst: eliecs::Entity) -> bool {
+            if !self.is_alive(src) || !self.is_alive(dst) {
+                return false;
+            }
+            if src != dst {
+                for ty in ComponentType::ALL {
+                    if let Some(c) = ty
+                        .is_cloned()
+                        .then(|| self.component_cloned(src.id, ty))
+                        .flatten()
+                    {
+                        self.insert_component(dst.id, c);
+                    }
+                }
+            }
+            true
+        }
+
+        /// Starts recording every mutation into a [`Journal`], replacing any journal already being
+        /// recorded. With `track_writes`, changes made through `*_mut` and `query_*_mut` are
+        /// recorded too, at the cost of cloning every component handed out mutably.
+        ///
+        /// Restoring a snapshot and applying a delta's free list aren't recorded.
+        pub fn start_journal(&mut self, track_writes: bool) {
+            *self.journal.get_mut() = Some(Journal::new(track_writes));
+        }
+
+        /// Stops recording and returns the journal, if one was being recorded.
+        pub fn take_journal(&mut self) -> Option<Journal> {
+            self.flush_journal();
+            self.journal.get_mut().take()
+        }
+
+        pub fn is_journaling(&self) -> bool {
+            unsafe { (*self.journal.get()).is_some() }
+        }
+
+        /// Records the writes through `*_mut` made since the last recorded mutation. This
+        /// happens on its own before every mutation, so it only matters at the end of a frame
+        /// or before looking at the journal. Writes through a `&mut` taken before the last flush
+        /// aren't seen anymore, see [`Journal`].
+        pub fn flush_journal(&self) {
+            let written = std::mem::take(unsafe { &mut *self.written.get() });
+            let mut seen = std::collections::HashSet::new();
+            for (id, previous) in written {
+                let ty = previous.component_type();
+                if !seen.insert((id, ty)) {
+                    continue;
+                }
+                if let (Some(e), Some(current)) =
+                    (self.get_entity_from_id(id), self.component_cloned(id, ty))
+                {
+                    if current.differs(&previous) {
+                        let m = eliecs::Mutation::Insert(e, current);
+                        let history = unsafe { &mut *self.history.get() };
+                        if history.is_open() {
+                            history.record(m.clone(), [eliecs::Mutation::Insert(e, previous)]);
+                        }
+                        if let Some(journal) = unsafe { &mut *self.journal.get() } {
+                            journal.record(m);
+                        }
+                    }
+                }
+            }
+        }
+
+        /// Hands the mutation about to happen to `id` to the journal and the open transaction,
+        /// if any. Called before the mutation, except for spawns, so the transaction can work out
+        /// how to undo it.
+        fn record(&self, id: u32, m: impl FnOnce(Entity) -> Mutation) {
+            let history = unsafe { &mut *self.history.get() };
+            if !history.is_open() {
+                history.record_untracked();
+                if !self.is_journaling() {
+                    return;
+                }
+            }
+            let Some(e) = self.get_entity_from_id(id) else {
+                return;
+            };
+            self.flush_journal();
+            let m = m(e);
+            if history.is_open() {
+                history.record(m.clone(), self.inverse_of(&m));
+            }
+            if let Some(journal) = unsafe { &mut *self.journal.get() } {
+                journal.record(m);
+            }
+        }
+
+        /// The mutations undoing `m`, in the reverse of the order they should be applied in.
+        fn inverse_of(&self, m: &Mutation) -> Vec<Mutation> {
+            match m {
+                eliecs::Mutation::Spawn(e) => vec![eliecs::Mutation::Despawn(*e)],
+                eliecs::Mutation::Despawn(e) => {
+                    let mut inverse = ComponentType::ALL
+                        .iter()
+                        .filter_map(|ty| self.component_cloned(e.id, *ty))
+                        .map(|c| eliecs::Mutation::Insert(*e, c))
+                        .collect::<Vec<_>>();
+                    inverse.push(eliecs::Mutation::Spawn(*e));
+                    inverse
+                }
+                eliecs::Mutation::Insert(e, c) => {
+                    vec![match self.component_cloned(e.id, c.component_type()) {
+                        Some(previous) => eliecs::Mutation::Insert(*e, previous),
+                        None => eliecs::Mutation::Remove(*e, c.component_type()),
+                    }]
+                }
+                eliecs::Mutation::Remove(e, ty) => self
+                    .component_cloned(e.id, *ty)
+                    .map(|previous| eliecs::Mutation::Insert(*e, previous))
+                    .into_iter()
+                    .collect(),
+            }
+        }
+
+        /// Whether writes through `*_mut` need their previous values kept, for the journal or
+        /// an open transaction.
+        fn tracks_writes(&self) -> bool {
+            unsafe { &*self.journal.get() }
+                .as_ref()
+                .is_some_and(|journal| journal.tracks_writes())
+                || unsafe { &*self.history.get() }.is_open()
+        }
+
+        fn record_write(&self, id: u32, previous: impl FnOnce() -> ComponentTypeContaining) {
+            if self.tracks_writes() {
+                unsafe { &mut *self.written.get() }.push((id, previous()));
+            }
+        }
+
+        /// Starts a transaction: everything done until the matching [`Ecs::commit`], including
+        /// writes through `*_mut` as far as [`Journal`] describes, is undone and redone as one
+        /// step. Transactions nest, only the outermost one ends up on the undo stack.
+        pub fn begin_transaction(&mut self) {
+            self.history.get_mut().begin();
+        }
+
+        /// Ends the innermost transaction. Returns `true` if it was the outermost one and it
+        /// changed something, which can now be undone.
+        pub fn commit(&mut self) -> bool {
+            self.flush_journal();
+            self.history.get_mut().commit()
+        }
+
+        pub fn in_transaction(&self) -> bool {
+            unsafe { &*self.history.get() }.is_open()
+        }
+
+        /// Undoes the last committed transaction, committing any that are still open first.
+        /// Despawned entities come back as the same [`Entity`], so handles to them stay valid.
+        ///
+        /// Returns `false` if there is nothing to undo, or if changes made outside transactions
+        /// since took an id it would spawn or despawned an entity it touches; the transaction
+        /// then stays on the undo stack.
+        pub fn undo(&mut self) -> bool {
+            self.flush_journal();
+            self.history.get_mut().commit_all();
+            let Some(t) = self.history.get_mut().pop_undo() else {
+                return false;
+            };
+            if !self.can_apply(t.inverse.iter().rev()) {
+                self.history.get_mut().push_undo(t);
+                return false;
+            }
+            self.history.get_mut().set_replaying(true);
+            for m in t.inverse.iter().rev() {
+                self.apply_mutation(m);
+            }
+            self.history.get_mut().set_replaying(false);
+            self.history.get_mut().push_redo(t);
+            true
+        }
+
+        /// Redoes the last undone transaction. Anything changed since clears the redo stack.
+        /// Returns `false` if there is nothing to redo, or if it can't be, like [`Ecs::undo`].
+        pub fn redo(&mut self) -> bool {
+            self.flush_journal();
+            self.history.get_mut().commit_all();
+            let Some(t) = self.history.get_mut().pop_redo() else {
+                return false;
+            };
+            if !self.can_apply(t.forward.iter()) {
+                self.history.get_mut().push_redo(t);
+                return false;
+            }
+            self.history.get_mut().set_replaying(true);
+            for m in &t.forward {
+                self.apply_mutation(m);
+            }
+            self.history.get_mut().set_replaying(false);
+            self.history.get_mut().push_undo(t);
+            true
+        }
+
+        /// Runs `f` so that either everything it does to the world happens, or nothing does:
+        /// returning `Err` or panicking rolls back every spawn, despawn, added or removed
+        /// component and write through `*_mut` it made.
+        ///
+        /// ```ignore
+        /// ecs.transaction(|tx| {
+        ///     let item = take_item(tx, from)?;
+        ///     give_item(tx, to, item)
+        /// })?;
+        /// ```
+        ///
+        /// Inside [`Ecs::begin_transaction`] it becomes part of the open transaction; otherwise
+        /// it isn't kept for [`Ecs::undo`].
+        pub fn transaction<R, E>(
+            &mut self,
+            f: impl FnOnce(&mut Ecs) -> Result<R, E>,
+        ) -> Result<R, E> {
+            // writes made before belong to whatever came before
+            self.flush_journal();
+            self.begin_transaction();
+            let outermost = self.history.get_mut().depth() == 1;
+            let mark = self.history.get_mut().mark();
+            // undoing spawns and despawns can't bring back which ids were free in what order
+            let free_list = self.free_list.clone();
+            let version_wraparounds = self.version_wraparounds;
+
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f(self)));
+            self.flush_journal();
+            if !matches!(result, Ok(Ok(_))) {
+                let inverse = self.history.get_mut().rollback_to(mark);
+                // rolling back shouldn't itself be recorded in the open transaction
+                let history = std::mem::take(self.history.get_mut());
+                for m in inverse.iter().rev() {
+                    self.apply_mutation(m);
+                }
+                *self.history.get_mut() = history;
+                self.free_list = free_list;
+                self.version_wraparounds = version_wraparounds;
+            }
+            if outermost {
+                // not kept for undo, but it still changed the world under the redo stack
+                if self.history.get_mut().abort().is_some_and(|t| !t.is_empty()) {
+                    self.history.get_mut().record_untracked();
+                }
+            } else {
+                self.history.get_mut().commit();
+            }
+
+            match result {
+                Ok(result) => result,
+                Err(panic) => std::panic::resume_unwind(panic),
+            }
+        }
+
+        pub fn history(&self) -> &History {
+            unsafe { &*self.history.get() }
+        }
+
+        /// Forgets every transaction that could be undone or redone.
+        pub fn clear_history(&mut self) {
+            self.history.get_mut().clear();
+        }
+
+        /// Applies one recorded mutation. Spawns give exactly the recorded entity, and do nothing
+        /// if its id is already taken.
+        pub fn apply_mutation(&mut self, m: &Mutation) {
+            match m {
+                eliecs::Mutation::Spawn(e) => {
+                    self.spawn_at(*e);
+                }
+                eliecs::Mutation::Despawn(e) => self.despawn(*e),
+                eliecs::Mutation::Insert(e, c) => {
+                    if self.is_alive(*e) {
+                        self.insert_component(e.id, c.clone());
+                    }
+                }
+                eliecs::Mutation::Remove(e, ty) => {
+                    if self.is_alive(*e) {
+                        self.remove_component(e.id, *ty);
+                    }
+                }
+            }
+        }
+
+        /// Whether applying `mutations` in order finds every id it spawns free and every entity
+        /// it touches otherwise alive, so none of them is skipped.
+        fn can_apply<'a>(&self, mutations: impl Iterator<Item = &'a Mutation>) -> bool {
+            // the ids the mutations so far spawned or despawned, with their version if alive
+            let mut changed = std::collections::HashMap::new();
+            for m in mutations {
+                let e = match m {
+                    eliecs::Mutation::Spawn(e)
+                    | eliecs::Mutation::Despawn(e)
+                    | eliecs::Mutation::Insert(e, _)
+                    | eliecs::Mutation::Remove(e, _) => *e,
+                };
+                let version = changed
+                    .get(&e.id)
+                    .copied()
+                    .unwrap_or_else(|| self.existence.get(e.id).copied());
+                match m {
+                    eliecs::Mutation::Spawn(_) if version.is_none() => {
+                        changed.insert(e.id, Some(e.version));
+                    }
+                    eliecs::Mutation::Despawn(_) if version == Some(e.version) => {
+                        changed.insert(e.id, None);
+                    }
+                    eliecs::Mutation::Insert(..) | eliecs::Mutation::Remove(..)
+                        if version == Some(e.version) => {}
+                    _ => return false,
+                }
+            }
+            true
+        }
+
+        /// The world after the first `step` mutations of `journal`, starting from `initial`,
+        /// which should be a snapshot of the world when the journal was started.
+        pub fn replay(initial: &Ecs, journal: &Journal, step: usize) -> Ecs {
+            let mut ecs = initial.clone();
+            for m in &journal.records[..step.min(journal.records.len())] {
+                ecs.apply_mutation(m);
+            }
+            ecs
+        }
+
+        pub fn despawn(&mut self, e: eliecs::Entity) {
+            self.take(e);
+        }
+
+        /// Despawns `e` and returns its components, e.g. to spawn it in another world.
+        pub fn take(&mut self, e: eliecs::Entity) -> Option<FatEntity> {
+            if !self.is_alive(e) {
+                return None;
+            }
+            self.record(e.id, eliecs::Mutation::Despawn);
+            self.existence.remove(e.id);
+
+            let mut data = FatEntity::new();
+            #(#despawn_per_component)*
+
+            let mut v = e;
+            v.version = if let Some(v) = v.version.checked_add(1) {
+                v
+            } else {
+                self.version_wraparounds += 1;
+                std::num::NonZeroU32::MIN
+            };
+            self.free_list.push(v);
+            Some(data)
+        }
+
+        /// Clones of `e`'s components; empty if `e` is dead.
+        pub fn snapshot_entity(&self, e: eliecs::Entity) -> FatEntity {
+            if !self.is_alive(e) {
+                return FatEntity::new();
+            }
+            FatEntity {
+                #(#snapshot_entity_fields),*
+            }
+        }
+
+        /// The types of the components `e` has, in [`ComponentType::ALL`] order; empty if `e` is
+        /// dead.
+        pub fn components_of(&self, e: eliecs::Entity) -> Vec<ComponentType> {
+            if !self.is_alive(e) {
+                return Vec::new();
+            }
+            ComponentType::ALL
+                .into_iter()
+                .filter(|&ty| self.has_component(e.id, ty))
+                .collect()
+        }
+
+        /// The prefabs [`Ecs::spawn_prefab`] spawns from. They aren't serialized or compared,
+        /// and clones of the world share them until one changes them, so snapshots stay cheap;
+        /// restoring a snapshot brings back the prefabs it had.
+        pub fn prefabs(&self) -> &Prefabs {
+            &self.prefabs
+        }
+
+        pub fn prefabs_mut(&mut self) -> &mut Prefabs {
+            std::sync::Arc::make_mut(&mut self.prefabs)
+        }
+
+        /// Spawns the prefab `name` with the components it inherits, then its children.
+        /// Nothing is spawned if the prefab or any of its parents or children is missing.
+        pub fn spawn_prefab(
+            &mut self,
+            name: &str,
+        ) -> Result<eliecs::PrefabInstance, eliecs::PrefabError> {
+            self.prefabs.validate(name)?;
+            Ok(self.spawn_prefab_tree(name))
+        }
+
+        fn spawn_prefab_tree(&mut self, name: &str) -> eliecs::PrefabInstance {
+            let prefabs = self.prefabs.clone();
+            eliecs::PrefabInstance {
+                root: self.spawn(prefabs.resolve(name).unwrap()),
+                children: prefabs
+                    .children(name)
+                    .unwrap()
+                    .into_iter()
+                    .map(|child| self.spawn_prefab_tree(child))
+                    .collect(),
+            }
+        }
+
+        /// A copy of the whole world, to hand back to [`Ecs::restore`] later.
+        pub fn snapshot(&self) -> Ecs {
+            self.clone()
+        }
+
+        /// Resets the world to `snapshot`, reusing this `Ecs`'s pool allocations.
+        pub fn restore(&mut self, snapshot: &Ecs) {
+            self.clone_from(snapshot);
+        }
+
+        /// Computes what changed from `old` to `new`. Applying the result to `old` with
+        /// [`Ecs::apply_delta`] makes it equal to `new`.
+        pub fn diff(old: &Ecs, new: &Ecs) -> WorldDelta {
+            let mut delta = WorldDelta::new();
+            for (id, version) in new.existence.iter() {
+                let e = Entity::new(id, *version);
+                if !old.is_alive(e) {
+                    delta.spawned.push(e);
+                }
+            }
+            for (id, version) in old.existence.iter() {
+                let e = Entity::new(id, *version);
+                if !new.is_alive(e) {
+                    delta.despawned.push(e);
+                }
+            }
+
+            #(#diff_per_component)*
+
+            delta.spawned.sort_by_key(|e| e.id);
+            delta.despawned.sort_by_key(|e| e.id);
+            delta.inserted.sort_by_key(|(e, _)| e.id);
+            delta.updated.sort_by_key(|(e, _)| e.id);
+            delta.removed.sort_by_key(|(e, _)| e.id);
+            // spawning and despawning touch the free list on their own, so send it whenever they
+            // happen even if it ends up the same
+            if old.free_list != new.free_list
+                || !delta.spawned.is_empty()
+                || !delta.despawned.is_empty()
+            {
+                delta.free_list = Some(new.free_list.clone());
+            }
+            delta
+        }
+
+        /// Applies a delta computed by [`Ecs::diff`]. Entities are spawned with exactly the
+        /// entity they had in the diffed world.
+        pub fn apply_delta(&mut self, delta: &WorldDelta) {
+            for e in &delta.despawned {
+                self.despawn(*e);
+            }
+            for e in &delta.spawned {
+                self.spawn_at(*e);
+            }
+            for (e, ty) in &delta.removed {
+                if self.is_alive(*e) {
+                    self.remove_component(e.id, *ty);
+                }
+            }
+            for (e, c) in delta.inserted.iter().chain(&delta.updated) {
+                if self.is_alive(*e) {
+                    self.insert_component(e.id, c.clone());
+                }
+            }
+            if let Some(free_list) = &delta.free_list {
+                self.free_list.clone_from(free_list);
+            }
+        }
+
+        /// A hash of the whole world that is the same on every machine for worlds that are `==`,
+        /// see [`eliecs::hash`]. Fails when a component's `Serialize` impl does.
+        pub fn state_hash(&self) -> Result<u64, eliecs::hash::HashError> {
+            Ok(self.state_hashes()?.combined())
+        }
+
+        /// [`Ecs::state_hash`] split up per pool, to find out which one differs.
+        pub fn state_hashes(&self) -> Result<StateHashes, eliecs::hash::HashError> {
+            Ok(StateHashes {
+                existence: eliecs::hash::hash_pool(&self.existence)?,
+                free_list: eliecs::hash::stable_hash(&self.free_list)?,
+                components: vec![#(#state_hash_per_component),*],
+            })
+        }
+
+        /// Collects every live entity and its components into an entity-centric [`Scene`],
+        /// ordered by entity id.
+        pub fn to_scene(&self) -> Scene {
+            let mut ids = self.existence.iter().map(|(id, _)| id).collect::<Vec<_>>();
+            ids.sort_unstable();
+
+            let mut scene = Scene::new();
+            for id in ids {
+                let mut components = Vec::new();
+                #(#scene_per_component)*
+                scene.entities.push(eliecs::SceneEntity {
+                    entity: self.get_entity_from_id(id).unwrap(),
+                    components,
+                });
+            }
+            scene
+        }
+
+        /// Spawns every entity of `scene` as a new entity and returns which entity each record
+        /// was loaded as.
+        ///
+        /// References between the loaded entities are rewritten through the returned map, see
+        /// [`eliecs::MapEntities`].
+        pub fn load_scene(&mut self, scene: Scene) -> eliecs::EntityMap {
+            let mut map = eliecs::EntityMap::new();
+            let entities = scene
+                .entities
+                .iter()
+                .map(|record| {
+                    let e = self.alloc_entity();
+                    map.insert(record.entity, e);
+                    e
+                })
+                .collect::<Vec<_>>();
+
+            for (e, record) in entities.into_iter().zip(scene.entities) {
+                let mut fat = record
+                    .components
+                    .into_iter()
+                    .fold(FatEntity::new(), |fat, c| c.add_to_fat_entity(fat));
+                eliecs::MapEntities::map_entities(&mut fat, &map);
+                self.insert_fat(e.id, fat);
+            }
+            map
+        }
+
+        /// Moves every live entity of `other` into this `Ecs`, giving each a fresh entity here, and
+        /// returns which entity each one became.
+        ///
+        /// Entity references inside the moved components are rewritten through the returned map,
+        /// see [`eliecs::MapEntities`]. Components `other` has on ids that aren't alive are
+        /// dropped.
+        pub fn append(&mut self, other: Ecs) -> eliecs::EntityMap {
+            let Ecs {
+                existence,
+                #(#ecs_fields_deser,)*
+                ..
+            } = other;
+
+            let mut ids = existence.iter().map(|(id, _)| id).collect::<Vec<_>>();
+            ids.sort_unstable();
+            let mut map = eliecs::EntityMap::new();
+            for id in ids {
+                let e = self.alloc_entity();
+                map.insert(Entity::new(id, *existence.get(id).unwrap()), e);
+            }
+
+            #(#append_per_component)*
+
+            map
+        }
+
+        #(#ecs_per_component_methods)*
+    }
+
+    impl Clone for Ecs {
+        fn clone(&self) -> Self {
+            Self {
+                existence: self.existence.clone(),
+                free_list: self.free_list.clone(),
+                version_wraparounds: self.version_wraparounds,
+                prefabs: self.prefabs.clone(),
+                // copies aren't journaled and can't be undone, the journal and history belong to
+                // the world they were recorded on
+                journal: std::cell::UnsafeCell::new(None),
+                history: std::cell::UnsafeCell::new(History::new()),
+                written: std::cell::UnsafeCell::new(Vec::new()),
+                borrows: eliecs::BorrowFlags::new(),
+                #(#ecs_fields_clone),*
+            }
+        }
+
+        fn clone_from(&mut self, source: &Self) {
+            self.existence.clone_from(&source.existence);
+            self.free_list.clone_from(&source.free_list);
+            self.version_wraparounds = source.version_wraparounds;
+            self.prefabs.clone_from(&source.prefabs);
+            #(#ecs_fields_clone_from)*
+        }
+    }
+
+    impl Default for Ecs {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl eliecs::replication::Replicate for Ecs {
+        type Component = ComponentTypeContaining;
+        type ComponentType = ComponentType;
+
+        fn replication_delta(
+            &self,
+            known: &eliecs::replication::Known<ComponentType>,
+            relevant: &mut dyn FnMut(Entity) -> bool,
+        ) -> WorldDelta {
+            let mut delta = WorldDelta::new();
+            // entities without replicated components aren't sent at all
+            let mut sent = Pool::<()>::new();
+            for (id, version) in self.existence.iter() {
+                let e = Entity::new(id, *version);
+                if (false #(|| #has_replicated)*) && relevant(e) {
+                    sent.insert(id, ());
+                    if !known.contains(e) {
+                        delta.spawned.push(e);
+                    }
+                }
+            }
+            for (e, types) in known.iter() {
+                if !(sent.contains(e.id) && self.is_alive(e)) {
+                    delta.despawned.push(e);
+                    continue;
+                }
+                for ty in types {
+                    if !self.has_component(e.id, ty) {
+                        delta.removed.push((e, ty));
+                    }
+                }
+            }
+
+            #(#replication_per_component)*
+
+            delta.spawned.sort_by_key(|e| e.id);
+            delta.despawned.sort_by_key(|e| e.id);
+            delta.inserted.sort_by_key(|(e, _)| e.id);
+            delta.updated.sort_by_key(|(e, _)| e.id);
+            delta.removed.sort_by_key(|(e, _)| e.id);
+            delta
+        }
+
+        fn component_hash(c: &ComponentTypeContaining) -> (ComponentType, u64) {
+            let hash = match c {
+                #(ComponentTypeContaining::#component_types(v) => eliecs::hash::stable_hash(v)),*
+            };
+            (c.component_type(), hash.expect("hashing a replicated component"))
+        }
+
+        fn apply_mapped_delta(
+            &mut self,
+            delta: WorldDelta,
+            map: &mut eliecs::EntityMap,
+            unresolved: &mut eliecs::replication::Unresolved<Self>,
+        ) {
+            for e in delta.despawned {
+                if let Some(local) = map.remove(e) {
+                    self.despawn(local);
+                }
+                unresolved.retain(|(owner, _), _| *owner != e);
+            }
+            for (e, ty) in delta.removed {
+                unresolved.remove(&(e, ty));
+                if let Some(local) = map.get(e) {
+                    self.remove_component(local.id, ty);
+                }
+            }
+            // components that referenced entities the client didn't have get another go whenever
+            // new ones arrive; the delta's own components come after them so they win
+            let retried = if delta.spawned.is_empty() {
+                Vec::new()
+            } else {
+                unresolved.drain().map(|((e, _), c)| (e, c)).collect()
+            };
+            for e in delta.spawned {
+                let local = self.alloc_entity();
+                map.insert(e, local);
+            }
+            for (e, c) in retried.into_iter().chain(delta.inserted).chain(delta.updated) {
+                let Some(local) = map.get(e) else {
+                    continue;
+                };
+                let ty = c.component_type();
+                if !ty.maps_entities() {
+                    self.insert_component(local.id, c);
+                    continue;
+                }
+                let mut mapped = c.clone();
+                let misses = map.misses();
+                eliecs::MapEntities::map_entities(&mut mapped, map);
+                if map.misses() > misses {
+                    unresolved.insert((e, ty), c);
+                } else {
+                    unresolved.remove(&(e, ty));
+                }
+                self.insert_component(local.id, mapped);
+            }
+        }
+    }
+
+    /// Alive entities by id with their components, e.g.
+    /// `Ecs { 0v1: CName("a"), CHealth { hp: 3 } }`. Entities without components show as `()`.
+    impl std::fmt::Debug for Ecs {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            struct Row<'a>(&'a Ecs, u32);
+            impl std::fmt::Debug for Row<'_> {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    self.0.fmt_components(self.1, f)
+                }
+            }
+
+            f.write_str("Ecs ")?;
+            f.debug_map()
+                .entries(self.sorted_entities().into_iter().map(|e| (e, Row(self, e.id))))
+                .finish()
+        }
+    }
+
+    /// One line per alive entity, e.g. `0v1: CName("a"), CHealth { hp: 3 }`.
+    impl std::fmt::Display for Ecs {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            for e in self.sorted_entities() {
+                write!(f, "{e:?}: ")?;
+                self.fmt_components(e.id, f)?;
+                writeln!(f)?;
+            }
+            Ok(())
+        }
+    }
+
+    // only with eliecs' `inspector` feature, which a `cfg` here would check on the user's crate
+    eliecs::__if_inspector! {
+        impl Ecs {
+            /// The type named `name`, if `e` is alive, for [`eliecs::inspector::Inspect`].
+            fn inspected_type(
+                &self,
+                e: eliecs::Entity,
+                name: &str,
+            ) -> Result<ComponentType, eliecs::reflect::ReflectError> {
+                if !self.is_alive(e) {
+                    return Err(eliecs::reflect::ReflectError(format!("{e:?} isn't alive")));
+                }
+                ComponentType::from_name(name)
+                    .ok_or_else(|| eliecs::reflect::ReflectError(format!("no component `{name}`")))
+            }
+        }
+
+        impl eliecs::inspector::Inspect for Ecs {
+            fn schema() -> eliecs::schema::Schema {
+                Ecs::schema()
+            }
+
+            fn component_name(name: &str) -> Option<&'static str> {
+                ComponentType::from_name(name).map(ComponentType::name)
+            }
+
+            fn find(&self, query: Option<&str>) -> Result<Vec<eliecs::Entity>, eliecs::QueryError> {
+                match query {
+                    Some(src) => self.query_text(src),
+                    None => Ok(self.sorted_entities()),
+                }
+            }
+
+            fn component_values(
+                &self,
+                e: eliecs::Entity,
+            ) -> Option<Vec<eliecs::inspector::NamedValue>> {
+                if !self.is_alive(e) {
+                    return None;
+                }
+                let components = self
+                    .components_of(e)
+                    .into_iter()
+                    .map(|ty| (ty.name(), self.component_value(e.id, ty).unwrap()))
+                    .collect();
+                Some(components)
+            }
+
+            fn field_value(
+                &self,
+                e: eliecs::Entity,
+                component: &str,
+                path: &str,
+            ) -> Result<eliecs::reflect::Value, eliecs::reflect::ReflectError> {
+                let ty = self.inspected_type(e, component)?;
+                self.get_field(e.id, ty, path)
+            }
+
+            fn set_field_value(
+                &mut self,
+                e: eliecs::Entity,
+                component: &str,
+                path: &str,
+                value: eliecs::reflect::Value,
+            ) -> Result<(), eliecs::reflect::ReflectError> {
+                let ty = self.inspected_type(e, component)?;
+                self.set_field(e.id, ty, path, value)
+            }
+
+            fn insert_value(
+                &mut self,
+                e: eliecs::Entity,
+                component: &str,
+                value: eliecs::reflect::Value,
+            ) -> Result<(), eliecs::reflect::ReflectError> {
+                let ty = self.inspected_type(e, component)?;
+                self.insert_component(e.id, ComponentTypeContaining::from_value(ty, value)?);
+                Ok(())
+            }
+
+            fn remove_named(
+                &mut self,
+                e: eliecs::Entity,
+                component: &str,
+            ) -> Result<bool, eliecs::reflect::ReflectError> {
+                let ty = self.inspected_type(e, component)?;
+                Ok(self.remove_component(e.id, ty))
+            }
+
+            fn spawn_values(
+                &mut self,
+                components: Vec<(String, eliecs::reflect::Value)>,
+            ) -> Result<eliecs::Entity, eliecs::reflect::ReflectError> {
+                let mut fat = FatEntity::new();
+                for (name, value) in components {
+                    let ty = ComponentType::from_name(&name).ok_or_else(|| {
+                        eliecs::reflect::ReflectError(format!("no component `{name}`"))
+                    })?;
+                    fat = ComponentTypeContaining::from_value(ty, value)?.add_to_fat_entity(fat);
+                }
+                Ok(self.spawn(fat))
+            }
+
+            fn despawn_entity(&mut self, e: eliecs::Entity) -> bool {
+                self.take(e).is_some()
+            }
+
+            fn changes(&self, old: &Self) -> eliecs::inspector::Changes {
+                let named = |c: ComponentTypeContaining| (c.component_type().name(), c.to_value());
+                let delta = Ecs::diff(old, self);
+                eliecs::inspector::Changes {
+                    spawned: delta.spawned,
+                    despawned: delta.despawned,
+                    inserted: delta.inserted.into_iter().map(|(e, c)| (e, named(c))).collect(),
+                    updated: delta.updated.into_iter().map(|(e, c)| (e, named(c))).collect(),
+                    removed: delta.removed.into_iter().map(|(e, ty)| (e, ty.name())).collect(),
+                    free_list: delta.free_list,
+                }
+            }
+        }
+    }
+
+    #ecs_eq
 
     impl serde::Serialize for Ecs {
         fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -414,6 +2690,12 @@ pub fn components(input: TokenStream) -> TokenStream {
                     Ok(Ecs {
                         existence,
                         free_list,
+                        version_wraparounds: 0,
+                        prefabs: std::sync::Arc::default(),
+                        journal: std::cell::UnsafeCell::new(None),
+                        history: std::cell::UnsafeCell::new(History::new()),
+                        written: std::cell::UnsafeCell::new(Vec::new()),
+                borrows: eliecs::BorrowFlags::new(),
                         #(#ecs_fields_deser),*
                     })
                 }
@@ -422,7 +2704,7 @@ pub fn components(input: TokenStream) -> TokenStream {
                     formatter.write_str("a serialized ECS")
                 }
             }
-            deserializer.deserialize_tuple(4, ECSVisitor)
+            deserializer.deserialize_tuple(#ecs_tuple_size, ECSVisitor)
         }
     }
         }